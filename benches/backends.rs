@@ -0,0 +1,59 @@
+// cargo bench --features pest --bench backends
+//
+// the winnow parser (`json::parse_json`) against the pest one (`json2::parse`) on the same
+// documents, both accept the same input and build the same `JsonValue`. run it on your own
+// machine for the numbers, criterion keeps the previous run to compare against
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use gammar::{json::parse_json, json2};
+
+const SMALL_OBJECT: &str = r#"{
+    "name": "John Doe",
+    "age": 30,
+    "is_student": false,
+    "marks": [90, -80, 85.1],
+    "address": {
+        "city": "New York",
+        "zip": 10001
+    }
+}"#;
+
+fn large_array(n: usize) -> String {
+    let values: Vec<String> = (0..n)
+        .map(|i| {
+            format!(
+                r#"{{"id": {}, "name": "user {}", "score": {}.5, "active": {}}}"#,
+                i,
+                i,
+                i % 100,
+                i % 2 == 0
+            )
+        })
+        .collect();
+    format!("[{}]", values.join(", "))
+}
+
+fn deeply_nested(depth: usize) -> String {
+    format!("{}1{}", r#"[1, "a", "#.repeat(depth), "]".repeat(depth))
+}
+
+fn bench_backends(c: &mut Criterion) {
+    let docs = [
+        ("small_object", SMALL_OBJECT.to_string()),
+        ("large_array", large_array(2_000)),
+        ("deeply_nested", deeply_nested(100)),
+    ];
+    let mut group = c.benchmark_group("backends");
+    for (name, input) in &docs {
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_with_input(BenchmarkId::new("winnow", name), input, |b, input| {
+            b.iter(|| parse_json(std::hint::black_box(input.as_bytes())).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("pest", name), input, |b, input| {
+            b.iter(|| json2::parse(std::hint::black_box(input.as_bytes())).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_backends);
+criterion_main!(benches);
@@ -0,0 +1,20 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use gammar::json::parse_json;
+
+fn numeric_array(n: usize) -> String {
+    let values: Vec<String> = (0..n).map(|i| (i * 7919).to_string()).collect();
+    format!("[{}]", values.join(", "))
+}
+
+fn bench_numeric_array(c: &mut Criterion) {
+    let input = numeric_array(10_000);
+    let mut group = c.benchmark_group("numeric_array");
+    group.throughput(Throughput::Bytes(input.len() as u64));
+    group.bench_function("parse_json", |b| {
+        b.iter(|| parse_json(std::hint::black_box(input.as_bytes())).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_numeric_array);
+criterion_main!(benches);
@@ -0,0 +1,29 @@
+// cargo run --example json_trace --features debug -- '[1, tru]'
+//
+// every parser is wrapped in a named `trace` span, with winnow's `debug` feature on they are
+// printed to stderr as the parse goes. `BStr` input is used so the remaining input shows up
+// as text rather than a list of bytes:
+//
+// > parse_value                                       | [1, tru]∅
+//  > alt                                              | [1, tru]∅
+//   > null                                            | [1, tru]∅
+//    > "null"                                         | [1, tru]∅
+//    < "null"                                         | backtrack
+//   < null                                            | backtrack
+//   ...
+//   > parse_array                                     | [1, tru]∅
+use gammar::json::{parse_value, ParseOptions, ParseState};
+use winnow::{error::ContextError, BStr, Parser};
+
+fn main() {
+    let input = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "[1, tru]".to_string());
+    let opts = ParseOptions::default();
+    let ret = (|i: &mut &BStr| parse_value::<_, ContextError>(i, &opts, 0, &ParseState::default()))
+        .parse(BStr::new(&input));
+    match ret {
+        Ok(v) => println!("{:?}", v),
+        Err(e) => println!("error at byte {}", e.offset()),
+    }
+}
@@ -0,0 +1,18 @@
+use anyhow::Result;
+use gammar::json::parse_json;
+
+fn main() -> Result<()> {
+    let s = r#"{
+        "name": "John Doe",
+        "age": 30,
+        "is_student": false,
+        "marks": [90, -80, 85.1],
+        "address": {
+            "city": "New York",
+            "zip": 10001
+        }
+    }"#;
+    let v = parse_json(s.as_bytes())?;
+    println!("{:#?}", v);
+    Ok(())
+}
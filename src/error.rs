@@ -0,0 +1,65 @@
+use core::fmt;
+
+// every offset is a byte offset into the original input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseError {
+    InvalidUtf8 { offset: usize },
+    UnexpectedChar { offset: usize },
+    UnexpectedEof { offset: usize },
+    // offset of the opening quote
+    UnterminatedString { offset: usize },
+    // a raw control character inside a string, RFC 8259 requires them to be escaped
+    UnescapedControlChar { offset: usize },
+    // offset of the `\\`: an escape RFC 8259 doesn't define, bad `\\u` digits or a lone
+    // UTF-16 surrogate
+    InvalidEscape { offset: usize },
+    InvalidNumber { offset: usize },
+    DepthExceeded { offset: usize },
+    ElementLimitExceeded { offset: usize },
+    TrailingData { offset: usize },
+    // nothing but whitespace, `offset` is where a value was expected
+    EmptyInput { offset: usize },
+}
+
+impl ParseError {
+    pub fn offset(&self) -> usize {
+        match *self {
+            ParseError::InvalidUtf8 { offset }
+            | ParseError::UnexpectedChar { offset }
+            | ParseError::UnexpectedEof { offset }
+            | ParseError::UnterminatedString { offset }
+            | ParseError::UnescapedControlChar { offset }
+            | ParseError::InvalidEscape { offset }
+            | ParseError::InvalidNumber { offset }
+            | ParseError::DepthExceeded { offset }
+            | ParseError::ElementLimitExceeded { offset }
+            | ParseError::TrailingData { offset }
+            | ParseError::EmptyInput { offset } => offset,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            // the offset is just the input length, not worth printing
+            ParseError::EmptyInput { .. } => {
+                return f.write_str("empty input: expected a JSON value")
+            }
+            ParseError::InvalidUtf8 { .. } => "invalid UTF-8",
+            ParseError::UnexpectedChar { .. } => "unexpected character",
+            ParseError::UnexpectedEof { .. } => "unexpected end of input",
+            ParseError::UnterminatedString { .. } => "unterminated string",
+            ParseError::UnescapedControlChar { .. } => "unescaped control character",
+            ParseError::InvalidEscape { .. } => "invalid escape",
+            ParseError::InvalidNumber { .. } => "invalid number",
+            ParseError::DepthExceeded { .. } => "nesting depth exceeded",
+            ParseError::ElementLimitExceeded { .. } => "element limit exceeded",
+            ParseError::TrailingData { .. } => "trailing data",
+        };
+        write!(f, "{} at byte {}", msg, self.offset())
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseError {}
@@ -1,295 +1,3710 @@
-use std::collections::HashMap;
+use alloc::{
+    collections::{btree_map, BTreeMap, BTreeSet},
+    format,
+    string::String,
+    vec::Vec,
+};
+use core::{
+    cell::Cell,
+    fmt::{self, Write as _},
+    hash::{Hash, Hasher},
+    ops::Range,
+};
 
 use anyhow::{anyhow, Result};
 use winnow::{
     ascii::{digit1, multispace0, Caseless},
-    combinator::{alt, delimited, opt, separated, separated_pair, trace},
-    error::{ContextError, ErrMode, ParserError},
-    stream::{AsBStr, AsChar, Compare, FindSlice, ParseSlice, Stream, StreamIsPartial},
-    token::take_until,
-    PResult, Parser,
+    combinator::{alt, cut_err, delimited, opt, preceded, separated_pair, terminated, trace},
+    error::{AddContext, ContextError, ErrMode, ErrorKind, ParserError, StrContext},
+    stream::{
+        AsBStr, AsChar, Compare, FindSlice, Located, Location, Offset, ParseSlice, Partial, Stream,
+        StreamIsPartial,
+    },
+    token::{any, one_of, take_till, take_while},
+    BStr, PResult, Parser,
 };
 
+use crate::error::ParseError;
+
 #[derive(Debug, Clone, PartialEq)]
-enum Num {
+pub enum Num {
     Int(i64),
     Float(f64),
+    // the number exactly as written, only produced with `ParseOptions::raw_numbers`
+    Raw(String),
 }
 
-#[allow(unused)]
-#[derive(Debug, Clone, PartialEq)]
-enum JsonValue {
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum JsonValue {
+    #[default]
     Null,
     Bool(bool),
     Number(Num),
     String(String),
     Array(Vec<JsonValue>),
-    Object(HashMap<String, JsonValue>),
+    Object(BTreeMap<String, JsonValue>),
 }
 
-fn main() -> Result<()> {
-    let s = r#"{
-        "name": "John Doe",
-        "age": 30,
-        "is_student": false,
-        "marks": [90, -80, 85.1],
-        "address": {
-            "city": "New York",
-            "zip": 10001
+const DEFAULT_MAX_DEPTH: usize = 128;
+
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    // accept non-standard syntax, e.g. single-quoted strings
+    pub relaxed: bool,
+    // maximum nesting of arrays/objects, guards against stack overflow on hostile input
+    pub max_depth: usize,
+    // maximum members of a single array/object and maximum values in the whole document,
+    // together with `max_depth` they bound the memory a hostile input can make us allocate
+    pub max_elements: usize,
+    pub max_nodes: usize,
+    // keep every number as `Num::Raw` text instead of converting it, e.g. to hand money amounts
+    // to a decimal library without going through `f64`. with `relaxed` the text is respelled as
+    // strict JSON first, `+1_000` is kept as `1000`
+    pub raw_numbers: bool,
+    // convert numbers as usual, but where writing the value back wouldn't give the source text
+    // (`1e3`, `1.50`, `-0`, or one too big for `i64`/`f64`) keep it as `Num::Raw`, so that
+    // serializing the tree reproduces every number byte for byte. with `relaxed` that's the
+    // number respelled as strict JSON, `+1.50` comes back as `1.50`
+    pub lossless_numbers: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            relaxed: false,
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_elements: usize::MAX,
+            max_nodes: usize::MAX,
+            raw_numbers: false,
+            lossless_numbers: false,
         }
-    }"#;
-    let v = parse_json(s.as_bytes())?;
-    println!("{:#?}", v);
-    Ok(())
+    }
 }
 
-fn parse_json(input: &[u8]) -> Result<JsonValue> {
-    let input = &mut (&*input);
-    parse_value(input).map_err(|e: ErrMode<ContextError>| anyhow!("Failed to parse JSON: {}", e))
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParseStats {
+    pub objects: usize,
+    pub arrays: usize,
+    pub strings: usize,
+    pub numbers: usize,
+    pub booleans: usize,
+    pub nulls: usize,
+    // nesting of the deepest container, a scalar document has depth 0
+    pub max_depth: usize,
 }
 
-fn parse_null<Input, Error>(input: &mut Input) -> PResult<(), Error>
-where
-    Input: StreamIsPartial + Stream + Compare<&'static str>,
-    Error: ParserError<Input>,
-{
-    "null".value(()).parse_next(input)
+// non-fatal findings of `parse_json_with_warnings`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    // a later member replaced an earlier one with the same key. `path` is the JSON Pointer of
+    // the member, `offset` where the repeated key starts
+    DuplicateKey {
+        key: String,
+        path: String,
+        offset: usize,
+    },
 }
 
-fn parse_bool<Input, Error>(input: &mut Input) -> PResult<bool, Error>
-where
-    Input: StreamIsPartial + Stream + Compare<&'static str>,
-    <Input as Stream>::Slice: ParseSlice<bool>,
-    Error: ParserError<Input>,
-{
-    alt(("true", "false")).parse_to().parse_next(input)
+// a node annotated with the byte range it was parsed from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spanned<T> {
+    pub value: T,
+    pub span: Range<usize>,
 }
 
-fn parse_number<Input, Error>(input: &mut Input) -> PResult<Num, Error>
-where
-    Input: StreamIsPartial
-        + Stream
-        + Compare<&'static str>
-        + Compare<Caseless<&'static str>>
-        + Compare<char>
-        + AsBStr,
-    <Input as Stream>::Token: AsChar + Clone,
-    <Input as Stream>::IterOffsets: Clone,
-    <Input as Stream>::Slice: ParseSlice<f64> + ParseSlice<i64>,
-    Error: ParserError<Input>,
-{
-    let sign = opt("-").map(|x| x.is_some()).parse_next(input)?;
-    let num = digit1.parse_to::<i64>().parse_next(input)?;
-    let ret: Result<(), ErrMode<ContextError>> = ".".value(()).parse_next(input);
-    if ret.is_ok() {
-        let frac = digit1.parse_to::<i64>().parse_next(input)?;
-        let num = format!("{}.{}", num, frac).parse::<f64>().unwrap();
-        Ok(Num::Float(if sign { -num } else { num }))
-    } else {
-        Ok(Num::Int(if sign { -num } else { num }))
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpannedValue {
+    Null,
+    Bool(bool),
+    Number(Num),
+    String(String),
+    Array(Vec<Spanned<SpannedValue>>),
+    Object(BTreeMap<String, Spanned<SpannedValue>>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonToken {
+    BeginObject,
+    EndObject,
+    BeginArray,
+    EndArray,
+    Colon,
+    Comma,
+    String(String),
+    Number(Num),
+    Bool(bool),
+    Null,
+}
+
+// opt-in wrapper giving `JsonValue` `Eq` + `Hash`: all NaNs are equal to each other and
+// `-0.0` equals `0.0`, everything else compares like `JsonValue` itself
+#[derive(Debug, Clone)]
+pub struct HashableValue(pub JsonValue);
+
+// serde-free mapping out of a parsed document, implement it by hand for your own types
+pub trait FromJson: Sized {
+    fn from_json(v: &JsonValue) -> Result<Self>;
+}
+
+impl JsonValue {
+    // an empty object, fill it with `insert`
+    pub fn object() -> Self {
+        JsonValue::Object(BTreeMap::new())
+    }
+
+    pub fn array() -> Self {
+        JsonValue::Array(Vec::new())
+    }
+
+    pub fn string(s: impl Into<String>) -> Self {
+        JsonValue::String(s.into())
+    }
+
+    pub fn int(n: i64) -> Self {
+        JsonValue::Number(Num::Int(n))
+    }
+
+    pub fn float(f: f64) -> Self {
+        JsonValue::Number(Num::Float(f))
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &JsonValue)> {
+        let map = match self {
+            JsonValue::Object(map) => Some(map),
+            _ => None,
+        };
+        map.into_iter().flatten().map(|(k, v)| (k.as_str(), v))
+    }
+
+    pub fn elements(&self) -> impl Iterator<Item = &JsonValue> {
+        let values = match self {
+            JsonValue::Array(values) => Some(values),
+            _ => None,
+        };
+        values.into_iter().flatten()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(map) => map.get(key),
+            _ => None,
+        }
+    }
+
+    pub fn get_as<T: FromJson>(&self, key: &str) -> Result<T> {
+        let v = self
+            .get(key)
+            .ok_or_else(|| anyhow!("missing field `{}`", key))?;
+        T::from_json(v).map_err(|e| anyhow!("field `{}`: {}", key, e))
+    }
+
+    // moves the value out and leaves `null` in its place, e.g. to own a member of a tree
+    // that is only borrowed mutably
+    pub fn take(&mut self) -> JsonValue {
+        core::mem::take(self)
+    }
+
+    // the consuming counterparts of the `FromJson` conversions: the payload is moved out
+    // instead of cloned, and a value of the wrong kind is handed back unchanged
+    pub fn into_string(self) -> Result<String, JsonValue> {
+        match self {
+            JsonValue::String(s) => Ok(s),
+            v => Err(v),
+        }
+    }
+
+    pub fn into_array(self) -> Result<Vec<JsonValue>, JsonValue> {
+        match self {
+            JsonValue::Array(values) => Ok(values),
+            v => Err(v),
+        }
+    }
+
+    pub fn into_object(self) -> Result<BTreeMap<String, JsonValue>, JsonValue> {
+        match self {
+            JsonValue::Object(map) => Ok(map),
+            v => Err(v),
+        }
+    }
+
+    pub fn into_i64(self) -> Result<i64, JsonValue> {
+        i64::from_json(&self).map_err(|_| self)
+    }
+
+    pub fn into_f64(self) -> Result<f64, JsonValue> {
+        f64::from_json(&self).map_err(|_| self)
+    }
+
+    pub fn into_bool(self) -> Result<bool, JsonValue> {
+        match self {
+            JsonValue::Bool(b) => Ok(b),
+            v => Err(v),
+        }
+    }
+
+    // the previous value under `key`, if any. only objects can be inserted into
+    pub fn insert(
+        &mut self,
+        key: impl Into<String>,
+        value: JsonValue,
+    ) -> Result<Option<JsonValue>> {
+        match self {
+            JsonValue::Object(map) => Ok(map.insert(key.into(), value)),
+            _ => Err(anyhow!("cannot insert into a non-object value")),
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<JsonValue> {
+        match self {
+            JsonValue::Object(map) => map.remove(key),
+            _ => None,
+        }
+    }
+
+    // in-place access to a member like `BTreeMap::entry`, `None` for a non-object value
+    pub fn entry(
+        &mut self,
+        key: impl Into<String>,
+    ) -> Option<btree_map::Entry<'_, String, JsonValue>> {
+        match self {
+            JsonValue::Object(map) => Some(map.entry(key.into())),
+            _ => None,
+        }
+    }
+
+    // calls `f` on every node in pre-order: a container before its elements, object members in
+    // key order. children are visited after `f` ran on their parent, so whatever `f` puts in
+    // place is walked too. keys are left alone
+    pub fn map_values(&mut self, mut f: impl FnMut(&mut JsonValue)) {
+        self.walk_mut(&mut f);
+    }
+
+    fn walk_mut(&mut self, f: &mut dyn FnMut(&mut JsonValue)) {
+        f(self);
+        match self {
+            JsonValue::Array(values) => values.iter_mut().for_each(|v| v.walk_mut(f)),
+            JsonValue::Object(map) => map.values_mut().for_each(|v| v.walk_mut(f)),
+            _ => {}
+        }
+    }
+
+    // RFC 6901 JSON Pointer, e.g. `/address/city` or `/marks/2`, with `~1` for `/` and `~0` for
+    // `~` inside a key. the empty pointer is the value itself
+    pub fn pointer(&self, ptr: &str) -> Option<&JsonValue> {
+        if ptr.is_empty() {
+            return Some(self);
+        }
+        ptr.strip_prefix('/')?
+            .split('/')
+            .try_fold(self, |v, token| {
+                let token = token.replace("~1", "/").replace("~0", "~");
+                match v {
+                    JsonValue::Object(map) => map.get(&token),
+                    // no sign and no leading zeros, `-` (past the end) never exists
+                    JsonValue::Array(values) => {
+                        let digits = !token.is_empty() && token.bytes().all(|b| b.is_ascii_digit());
+                        if !digits || (token.len() > 1 && token.starts_with('0')) {
+                            return None;
+                        }
+                        values.get(token.parse::<usize>().ok()?)
+                    }
+                    _ => None,
+                }
+            })
     }
 }
 
-fn parse_string<Input, Error>(input: &mut Input) -> PResult<String, Error>
-where
-    Input: StreamIsPartial
-        + Stream
-        + Compare<&'static str>
-        + Compare<Caseless<&'static str>>
-        + Compare<char>
-        + AsBStr
-        + FindSlice<char>,
-    <Input as Stream>::Token: AsChar + Clone,
-    <Input as Stream>::IterOffsets: Clone,
-    <Input as Stream>::Slice: ParseSlice<String>,
-    Error: ParserError<Input>,
-{
-    let ret = delimited('"', take_until(0.., '"'), '"')
-        .parse_to()
-        .parse_next(input)?;
-    Ok(ret)
+impl FromJson for i64 {
+    fn from_json(v: &JsonValue) -> Result<Self> {
+        match v {
+            JsonValue::Number(Num::Int(n)) => Ok(*n),
+            JsonValue::Number(Num::Raw(s)) => s.parse().map_err(|_| anyhow!("expected an integer")),
+            _ => Err(anyhow!("expected an integer")),
+        }
+    }
 }
 
-pub fn sep_with_space<Input, Output, Error, ParseNext>(
-    mut parser: ParseNext,
-) -> impl Parser<Input, (), Error>
-where
-    Input: Stream + StreamIsPartial,
-    <Input as Stream>::Token: AsChar + Clone,
-    Error: ParserError<Input>,
-    ParseNext: Parser<Input, Output, Error>,
-{
-    trace("sep_with_space", move |input: &mut Input| {
-        let _ = multispace0.parse_next(input)?;
-        parser.parse_next(input)?;
-        multispace0.parse_next(input)?;
-        Ok(())
-    })
+// integers widen to floats, the other way round is an error
+impl FromJson for f64 {
+    fn from_json(v: &JsonValue) -> Result<Self> {
+        match v {
+            JsonValue::Number(Num::Float(n)) => Ok(*n),
+            JsonValue::Number(Num::Int(n)) => Ok(*n as f64),
+            JsonValue::Number(Num::Raw(s)) => s.parse().map_err(|_| anyhow!("expected a number")),
+            _ => Err(anyhow!("expected a number")),
+        }
+    }
 }
 
-fn parse_array<Input, Error>(input: &mut Input) -> PResult<Vec<JsonValue>, Error>
-where
-    Input: StreamIsPartial
-        + Stream
-        + Compare<&'static str>
-        + Compare<Caseless<&'static str>>
-        + Compare<char>
-        + AsBStr
-        + FindSlice<char>,
-    <Input as Stream>::Token: AsChar + Clone,
-    <Input as Stream>::IterOffsets: Clone,
-    <Input as Stream>::Slice:
-        ParseSlice<String> + ParseSlice<bool> + ParseSlice<i64> + ParseSlice<f64> + AsBStr,
-    Error: ParserError<Input>,
-{
-    let sep1 = sep_with_space('[');
-    let sep2 = sep_with_space(']');
-    let sep_comma = sep_with_space(',');
-    let parse_values = separated(0.., parse_value, sep_comma);
-    delimited(sep1, parse_values, sep2).parse_next(input)
+impl FromJson for bool {
+    fn from_json(v: &JsonValue) -> Result<Self> {
+        match v {
+            JsonValue::Bool(b) => Ok(*b),
+            _ => Err(anyhow!("expected a boolean")),
+        }
+    }
 }
 
-fn parse_object<Input, Error>(input: &mut Input) -> PResult<HashMap<String, JsonValue>, Error>
-where
-    Input: StreamIsPartial
-        + Stream
-        + Compare<&'static str>
-        + Compare<Caseless<&'static str>>
-        + Compare<char>
-        + AsBStr
-        + FindSlice<char>,
-    <Input as Stream>::Token: AsChar + Clone,
-    <Input as Stream>::IterOffsets: Clone,
-    <Input as Stream>::Slice:
-        ParseSlice<String> + ParseSlice<bool> + ParseSlice<i64> + ParseSlice<f64> + AsBStr,
-    Error: ParserError<Input>,
-{
-    let sep1 = sep_with_space('{');
-    let sep2 = sep_with_space('}');
-    let sep_colon = sep_with_space(':');
-    let sep_comma = sep_with_space(',');
-    let parse_kv_pair = separated_pair(parse_string, sep_colon, parse_value);
-    let parse_kv = separated(1.., parse_kv_pair, sep_comma);
-    delimited(sep1, parse_kv, sep2).parse_next(input)
+impl FromJson for String {
+    fn from_json(v: &JsonValue) -> Result<Self> {
+        match v {
+            JsonValue::String(s) => Ok(s.clone()),
+            _ => Err(anyhow!("expected a string")),
+        }
+    }
 }
 
-fn parse_value<Input, Error>(input: &mut Input) -> PResult<JsonValue, Error>
-where
-    Input: StreamIsPartial
-        + Stream
-        + Compare<&'static str>
-        + Compare<Caseless<&'static str>>
-        + Compare<char>
-        + AsBStr
-        + FindSlice<char>,
-    <Input as Stream>::Token: AsChar + Clone,
-    <Input as Stream>::IterOffsets: Clone,
-    <Input as Stream>::Slice:
-        ParseSlice<String> + ParseSlice<bool> + ParseSlice<i64> + ParseSlice<f64> + AsBStr,
-    Error: ParserError<Input>,
-{
-    alt((
-        parse_null.value(JsonValue::Null),
-        parse_bool.map(JsonValue::Bool),
-        parse_number.map(JsonValue::Number),
-        parse_string.map(JsonValue::String),
-        parse_array.map(JsonValue::Array),
-        parse_object.map(JsonValue::Object),
-    ))
-    .parse_next(input)
+impl<T: FromJson> FromJson for Vec<T> {
+    fn from_json(v: &JsonValue) -> Result<Self> {
+        match v {
+            JsonValue::Array(values) => values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| T::from_json(v).map_err(|e| anyhow!("index {}: {}", i, e)))
+                .collect(),
+            _ => Err(anyhow!("expected an array")),
+        }
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+impl From<JsonValue> for HashableValue {
+    fn from(v: JsonValue) -> Self {
+        Self(v)
+    }
+}
 
-    #[test]
-    fn test_parse_null() -> PResult<(), ContextError> {
-        let input = "null";
-        parse_null(&mut (&*input))?;
-        Ok(())
+impl PartialEq for HashableValue {
+    fn eq(&self, other: &Self) -> bool {
+        value_eq(&self.0, &other.0)
     }
+}
 
-    #[test]
-    fn test_parse_bool() -> PResult<(), ContextError> {
-        let input = "true";
-        let result = parse_bool(&mut (&*input))?;
-        assert!(result);
+impl Eq for HashableValue {}
 
-        let input = "false";
-        let result = parse_bool(&mut (&*input))?;
-        assert!(!result);
-        Ok(())
+impl Hash for HashableValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash_value(&self.0, state);
     }
+}
 
-    #[test]
-    fn test_parse_number() -> PResult<(), ContextError> {
-        let input = "123.45";
-        let result = parse_number(&mut (&*input))?;
-        assert_eq!(result, Num::Float(123.45));
+#[derive(Debug, Clone, Default)]
+pub struct WriteOptions {
+    // write `/` as `\/`, so a string can't close the `<script>` element the JSON is inlined in
+    pub escape_solidus: bool,
+}
 
-        let input = "-123.45";
-        let result = parse_number(&mut (&*input))?;
-        assert_eq!(result, Num::Float(-123.45));
-        Ok(())
+// `JsonValue::display_with`, formats the value with the given options
+pub struct JsonDisplay<'a> {
+    value: &'a JsonValue,
+    opts: &'a WriteOptions,
+}
+
+impl JsonValue {
+    pub fn display_with<'a>(&'a self, opts: &'a WriteOptions) -> JsonDisplay<'a> {
+        JsonDisplay { value: self, opts }
     }
+}
 
-    #[test]
-    fn test_parse_string() -> PResult<(), ContextError> {
-        let input = r#""Hello, World!""#;
-        let result = parse_string(&mut (&*input))?;
-        assert_eq!(result, "Hello, World!");
-        Ok(())
+// compact JSON, `to_string()` builds the whole text. a float is written like JS would, see
+// `write_float`, and NaN and the infinities, which JSON can't express, become `null`
+impl fmt::Display for JsonValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_value(f, self, &WriteOptions::default())
     }
+}
 
-    #[test]
-    fn test_parse_array() -> PResult<(), ContextError> {
-        let input = r#"[1, 2, 3]"#;
-        let result = parse_array(&mut (&*input))?;
-        assert_eq!(
-            result,
-            vec![
-                JsonValue::Number(Num::Int(1)),
-                JsonValue::Number(Num::Int(2)),
-                JsonValue::Number(Num::Int(3))
-            ]
-        );
+impl fmt::Display for JsonDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_value(f, self.value, self.opts)
+    }
+}
 
-        let input = r#"["a", null, 1]"#;
-        let result = parse_array(&mut (&*input))?;
-        assert_eq!(
-            result,
-            vec![
-                JsonValue::String("a".to_string()),
-                JsonValue::Null,
-                JsonValue::Number(Num::Int(1))
-            ]
-        );
+fn write_value(f: &mut fmt::Formatter<'_>, v: &JsonValue, opts: &WriteOptions) -> fmt::Result {
+    match v {
+        JsonValue::Null => f.write_str("null"),
+        JsonValue::Bool(b) => write!(f, "{}", b),
+        JsonValue::Number(Num::Int(n)) => write!(f, "{}", n),
+        JsonValue::Number(Num::Float(n)) if !n.is_finite() => f.write_str("null"),
+        JsonValue::Number(Num::Float(n)) => write_float(f, *n),
+        JsonValue::Number(Num::Raw(s)) => f.write_str(s),
+        JsonValue::String(s) => write_string(f, s, opts),
+        JsonValue::Array(values) => {
+            f.write_str("[")?;
+            for (i, v) in values.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(",")?;
+                }
+                write_value(f, v, opts)?;
+            }
+            f.write_str("]")
+        }
+        JsonValue::Object(map) => {
+            f.write_str("{")?;
+            for (i, (k, v)) in map.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(",")?;
+                }
+                write_string(f, k, opts)?;
+                f.write_str(":")?;
+                write_value(f, v, opts)?;
+            }
+            f.write_str("}")
+        }
+    }
+}
 
-        let input = r#"[123.45, 122.3]"#;
-        let result = parse_array(&mut (&*input))?;
-        println!("{:?}", result);
-        assert_eq!(
-            result,
-            vec![
-                JsonValue::Number(Num::Float(123.45)),
-                JsonValue::Number(Num::Float(122.3))
-            ]
-        );
-        Ok(())
+// ECMAScript's Number::toString layout around the shortest digits that round-trip: plain
+// decimals from 1e-7 up to 1e21, exponent notation outside of that (`1e+21`, `1e-7`). unlike
+// JS an integral float keeps a `.0` so it reads back as a float
+fn write_float(f: &mut fmt::Formatter<'_>, n: f64) -> fmt::Result {
+    // `{:e}` gives the same shortest digits as `{}`, as `d.ddde-x`
+    let mut buf = FloatBuf::default();
+    write!(buf, "{:e}", n.abs())?;
+    let (mantissa, exp) = buf.as_str().split_once('e').ok_or(fmt::Error)?;
+    let exp: i32 = exp.parse().map_err(|_| fmt::Error)?;
+    let mut digits = FloatBuf::default();
+    for part in mantissa.split('.') {
+        digits.write_str(part)?;
     }
+    let digits = digits.as_str();
+    // the value is `0.digits * 10^point`
+    let (len, point) = (digits.len() as i32, exp + 1);
 
-    #[test]
+    if n.is_sign_negative() {
+        f.write_str("-")?;
+    }
+    if len <= point && point <= 21 {
+        f.write_str(digits)?;
+        for _ in len..point {
+            f.write_str("0")?;
+        }
+        f.write_str(".0")
+    } else if 0 < point && point <= 21 {
+        let (int, frac) = digits.split_at(point as usize);
+        write!(f, "{}.{}", int, frac)
+    } else if -6 < point && point <= 0 {
+        f.write_str("0.")?;
+        for _ in point..0 {
+            f.write_str("0")?;
+        }
+        f.write_str(digits)
+    } else {
+        let sign = if exp < 0 { '-' } else { '+' };
+        write!(f, "{}e{}{}", mantissa, sign, exp.abs())
+    }
+}
+
+// `{:e}` of a finite f64 is at most 17 digits, a point and a 4 character exponent
+#[derive(Default)]
+struct FloatBuf {
+    buf: [u8; 32],
+    len: usize,
+}
+
+impl FloatBuf {
+    fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or_default()
+    }
+}
+
+impl fmt::Write for FloatBuf {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        self.buf
+            .get_mut(self.len..end)
+            .ok_or(fmt::Error)?
+            .copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str, opts: &WriteOptions) -> fmt::Result {
+    f.write_str("\"")?;
+    // runs of characters that need no escaping are written in one go
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        let escaped = match c {
+            '"' => Some("\\\""),
+            '\\' => Some("\\\\"),
+            '/' if opts.escape_solidus => Some("\\/"),
+            '\n' => Some("\\n"),
+            '\r' => Some("\\r"),
+            '\t' => Some("\\t"),
+            '\u{8}' => Some("\\b"),
+            '\u{c}' => Some("\\f"),
+            c if c < ' ' => None,
+            _ => continue,
+        };
+        f.write_str(&s[start..i])?;
+        match escaped {
+            Some(escaped) => f.write_str(escaped)?,
+            None => write!(f, "\\u{:04x}", c as u32)?,
+        }
+        start = i + c.len_utf8();
+    }
+    f.write_str(&s[start..])?;
+    f.write_str("\"")
+}
+
+// streams `value` to `w` as it is formatted, the text is never held in memory as a whole. wrap
+// an unbuffered sink like a `File` in a `BufWriter`
+#[cfg(feature = "std")]
+pub fn to_writer(value: &JsonValue, w: impl std::io::Write) -> std::io::Result<()> {
+    to_writer_with_opts(value, w, &WriteOptions::default())
+}
+
+#[cfg(feature = "std")]
+pub fn to_writer_with_opts(
+    value: &JsonValue,
+    mut w: impl std::io::Write,
+    opts: &WriteOptions,
+) -> std::io::Result<()> {
+    write!(w, "{}", value.display_with(opts))
+}
+
+// drops the whitespace between tokens and copies everything else, strings included, byte for
+// byte. only checked as far as keeping strings intact needs: brackets have to balance, strings
+// have to end, and whitespace between two bare tokens (`1 2`) can't be dropped without gluing
+// them together. anything else invalid comes out as invalid as it went in
+pub fn minify(input: &str) -> Result<String> {
+    let bytes = input.as_bytes();
+    let mut ret = String::with_capacity(input.len());
+    // closing brackets still expected, innermost last
+    let mut open = Vec::new();
+    let mut i = 0;
+    // whether a bare token ended right before a run of whitespace
+    let mut after_bare = false;
+    let mut gap = false;
+    while let Some(&b) = bytes.get(i) {
+        match b {
+            b' ' | b'\t' | b'\n' | b'\r' => {
+                gap = true;
+                i += 1;
+                continue;
+            }
+            b'"' => {
+                let start = i;
+                i += 1;
+                loop {
+                    match bytes.get(i) {
+                        Some(b'"') => break,
+                        Some(b'\\') => i += 2,
+                        Some(_) => i += 1,
+                        None => {
+                            return Err(anyhow!(ParseError::UnterminatedString { offset: start }))
+                        }
+                    }
+                }
+                i += 1;
+                ret.push_str(&input[start..i]);
+                after_bare = false;
+            }
+            b'{' | b'[' => {
+                open.push(if b == b'{' { b'}' } else { b']' });
+                ret.push(b as char);
+                i += 1;
+                after_bare = false;
+            }
+            b'}' | b']' => {
+                if open.pop() != Some(b) {
+                    return Err(anyhow!(ParseError::UnexpectedChar { offset: i }));
+                }
+                ret.push(b as char);
+                i += 1;
+                after_bare = false;
+            }
+            b':' | b',' => {
+                ret.push(b as char);
+                i += 1;
+                after_bare = false;
+            }
+            // a number or `true`/`false`/`null`, copied up to the next delimiter
+            _ => {
+                if after_bare && gap {
+                    return Err(anyhow!(ParseError::UnexpectedChar { offset: i }));
+                }
+                let start = i;
+                while bytes.get(i).is_some_and(|&b| !ends_bare_token(b)) {
+                    i += 1;
+                }
+                ret.push_str(&input[start..i]);
+                after_bare = true;
+            }
+        }
+        gap = false;
+    }
+    if !open.is_empty() {
+        return Err(anyhow!(ParseError::UnexpectedEof {
+            offset: input.len()
+        }));
+    }
+    if ret.is_empty() {
+        return Err(anyhow!(ParseError::EmptyInput {
+            offset: input.len()
+        }));
+    }
+    Ok(ret)
+}
+
+fn ends_bare_token(b: u8) -> bool {
+    matches!(
+        b,
+        b' ' | b'\t' | b'\n' | b'\r' | b'"' | b'{' | b'}' | b'[' | b']' | b':' | b','
+    )
+}
+
+fn float_bits(f: f64) -> u64 {
+    if f.is_nan() {
+        f64::NAN.to_bits()
+    } else if f == 0.0 {
+        0
+    } else {
+        f.to_bits()
+    }
+}
+
+fn value_eq(a: &JsonValue, b: &JsonValue) -> bool {
+    match (a, b) {
+        (JsonValue::Number(Num::Float(a)), JsonValue::Number(Num::Float(b))) => {
+            float_bits(*a) == float_bits(*b)
+        }
+        (JsonValue::Array(a), JsonValue::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| value_eq(a, b))
+        }
+        (JsonValue::Object(a), JsonValue::Object(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .zip(b)
+                    .all(|((ka, va), (kb, vb))| ka == kb && value_eq(va, vb))
+        }
+        (a, b) => a == b,
+    }
+}
+
+fn hash_value<H: Hasher>(v: &JsonValue, state: &mut H) {
+    core::mem::discriminant(v).hash(state);
+    match v {
+        JsonValue::Null => {}
+        JsonValue::Bool(b) => b.hash(state),
+        JsonValue::Number(Num::Int(n)) => {
+            0u8.hash(state);
+            n.hash(state);
+        }
+        JsonValue::Number(Num::Float(f)) => {
+            1u8.hash(state);
+            float_bits(*f).hash(state);
+        }
+        JsonValue::Number(Num::Raw(s)) => {
+            2u8.hash(state);
+            s.hash(state);
+        }
+        JsonValue::String(s) => s.hash(state),
+        JsonValue::Array(values) => {
+            values.len().hash(state);
+            for v in values {
+                hash_value(v, state);
+            }
+        }
+        JsonValue::Object(map) => {
+            map.len().hash(state);
+            for (k, v) in map {
+                k.hash(state);
+                hash_value(v, state);
+            }
+        }
+    }
+}
+
+// windows tools like to prefix UTF-8 files with a byte order mark, it is skipped but still
+// counted in byte offsets
+const BOM: &[u8; 3] = b"\xEF\xBB\xBF";
+
+pub fn parse_json(input: &[u8]) -> Result<JsonValue> {
+    parse_json_with_opts(input, &ParseOptions::default())
+}
+
+pub fn parse_json_with_opts(input: &[u8], opts: &ParseOptions) -> Result<JsonValue> {
+    let len = input.len();
+    parse_document(&mut (&*input), opts, |i| len - i.len())
+}
+
+// `offset` turns what is left of the input into a byte offset for errors
+fn parse_document<Input, N>(
+    input: &mut Input,
+    opts: &ParseOptions,
+    offset: impl Fn(&Input) -> usize,
+) -> Result<N>
+where
+    Input: StreamIsPartial
+        + Stream
+        + Position
+        + Compare<&'static str>
+        + Compare<Caseless<&'static str>>
+        + Compare<char>
+        + Compare<&'static [u8; 3]>
+        + AsBStr
+        + FindSlice<char>,
+    <Input as Stream>::Token: AsChar + Clone,
+    <Input as Stream>::IterOffsets: Clone,
+    <Input as Stream>::Slice:
+        ParseSlice<String> + ParseSlice<bool> + ParseSlice<i64> + ParseSlice<f64> + AsBStr,
+    N: ValueBuilder,
+{
+    check_utf8(input.as_bstr())?;
+    let ws = |i: &mut Input| skip_ws(i, opts);
+    let err = |e, input: &Input| json_error(e, offset(input), input.eof_offset() == 0);
+    (opt(BOM), ws)
+        .parse_next(input)
+        .map_err(|e| err(e, input))?;
+    if input.eof_offset() == 0 {
+        return Err(anyhow!(ParseError::EmptyInput {
+            offset: offset(input)
+        }));
+    }
+    let state = ParseState::default();
+    let ret = terminated(|i: &mut Input| parse_node(i, opts, 0, &state), ws)
+        .parse_next(input)
+        .map_err(|e| err(e, input))?;
+    if input.eof_offset() != 0 {
+        return Err(anyhow!(ParseError::TrailingData {
+            offset: offset(input)
+        }));
+    }
+    Ok(ret)
+}
+
+// test helper comparing two documents by value, so whitespace and key order don't matter.
+// panics, like `assert_eq!`, if either side is invalid or they differ
+#[track_caller]
+pub fn assert_json_eq(a: &str, b: &str) {
+    let parse = |s: &str| parse_json(s.as_bytes()).unwrap_or_else(|e| panic!("{:?}: {}", s, e));
+    let (left, right) = (parse(a), parse(b));
+    assert_eq!(left, right, "{} != {}", a, b);
+}
+
+// `JsonValue` owns all of its data, so the buffer can be dropped right after parsing
+pub fn parse_json_owned(input: Vec<u8>) -> Result<JsonValue> {
+    parse_json(&input)
+}
+
+// reads the reader to the end without blocking the executor, then parses the buffer in one go;
+// there is no incremental parsing, the whole document is held in memory
+#[cfg(feature = "tokio")]
+pub async fn parse_json_async(reader: impl tokio::io::AsyncRead) -> Result<JsonValue> {
+    use tokio::io::AsyncReadExt;
+
+    let mut input = Vec::new();
+    core::pin::pin!(reader).read_to_end(&mut input).await?;
+    parse_json_owned(input)
+}
+
+// one value off the front of a buffer that is still filling up, e.g. from a socket. running out
+// of input mid-value is `ErrMode::Incomplete` rather than an error: append more bytes and call
+// again. a bare top-level number is only complete once something follows it or the input is
+// marked complete. the value's trailing whitespace is left in `input`
+pub fn parse_json_partial(input: &mut Partial<&[u8]>) -> PResult<JsonValue> {
+    let opts = ParseOptions::default();
+    skip_ws(input, &opts)?;
+    parse_value(input, &opts, 0, &ParseState::default())
+}
+
+pub fn parse_json_prefix(input: &[u8], opts: &ParseOptions) -> Result<(JsonValue, usize)> {
+    let rest = &mut Located::new(input);
+    let ret = preceded(
+        (opt(BOM), |i: &mut Located<&[u8]>| skip_ws(i, opts)),
+        |i: &mut Located<&[u8]>| parse_node(i, opts, 0, &ParseState::default()),
+    )
+    .parse_next(rest)
+    .map_err(|e| json_error(e, rest.location(), rest.is_empty()))?;
+    // only what was consumed has to be valid, the rest belongs to the caller
+    let len = rest.location();
+    check_utf8(&input[..len])?;
+    Ok((ret, len))
+}
+
+// stats are gathered in a separate walk so `parse_json` itself pays nothing for them
+pub fn parse_json_with_stats(input: &[u8]) -> Result<(JsonValue, ParseStats)> {
+    let v = parse_json(input)?;
+    let mut stats = ParseStats::default();
+    stats.collect(&v, 0);
+    Ok((v, stats))
+}
+
+// a container open while walking the tokens of `parse_json_with_warnings`
+enum Frame {
+    Object {
+        seen: BTreeSet<String>,
+        key: Option<String>,
+        expect_key: bool,
+    },
+    Array {
+        index: usize,
+    },
+}
+
+// `parse_json` keeping its last-wins semantics for repeated keys, but reporting each of them.
+// the keys are found by a second, token level pass over the already validated input
+pub fn parse_json_with_warnings(input: &[u8]) -> Result<(JsonValue, Vec<Warning>)> {
+    let v = parse_json(input)?;
+    let mut warnings = Vec::new();
+    let mut stack: Vec<Frame> = Vec::new();
+    for token in tokenize(input, &ParseOptions::default()) {
+        let Spanned { value, span } = token?;
+        match value {
+            JsonToken::BeginObject => stack.push(Frame::Object {
+                seen: BTreeSet::new(),
+                key: None,
+                expect_key: true,
+            }),
+            JsonToken::BeginArray => stack.push(Frame::Array { index: 0 }),
+            JsonToken::EndObject | JsonToken::EndArray => {
+                stack.pop();
+            }
+            JsonToken::Comma => match stack.last_mut() {
+                Some(Frame::Object { expect_key, .. }) => *expect_key = true,
+                Some(Frame::Array { index }) => *index += 1,
+                None => {}
+            },
+            JsonToken::String(s) => {
+                let Some(Frame::Object {
+                    seen,
+                    key,
+                    expect_key: expect_key @ true,
+                }) = stack.last_mut()
+                else {
+                    continue;
+                };
+                *expect_key = false;
+                *key = Some(s.clone());
+                if !seen.insert(s.clone()) {
+                    warnings.push(Warning::DuplicateKey {
+                        key: s,
+                        path: frames_pointer(&stack),
+                        offset: span.start,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok((v, warnings))
+}
+
+// the JSON Pointer of the value the innermost frame is at
+fn frames_pointer(stack: &[Frame]) -> String {
+    let mut ret = String::new();
+    for frame in stack {
+        ret.push('/');
+        match frame {
+            Frame::Object { key, .. } => {
+                let key = key.as_deref().unwrap_or_default();
+                ret.push_str(&key.replace('~', "~0").replace('/', "~1"));
+            }
+            Frame::Array { index } => ret.push_str(&format!("{}", index)),
+        }
+    }
+    ret
+}
+
+// `address.city` for object members, `marks[2]` for array elements, empty containers are kept
+// as leaves. `.`, `[`, `]` and `\` inside a key are escaped with a `\`, so `{"a.b": 1}` and
+// `{"a": {"b": 1}}` don't both end up as `a.b`
+pub fn flatten(v: &JsonValue) -> Vec<(String, JsonValue)> {
+    let mut ret = Vec::new();
+    flatten_into(v, String::new(), &mut ret);
+    ret
+}
+
+fn flatten_into(v: &JsonValue, path: String, ret: &mut Vec<(String, JsonValue)>) {
+    match v {
+        JsonValue::Array(values) if !values.is_empty() => {
+            for (i, v) in values.iter().enumerate() {
+                flatten_into(v, format!("{}[{}]", path, i), ret);
+            }
+        }
+        JsonValue::Object(map) if !map.is_empty() => {
+            for (k, v) in map {
+                let key = if path.is_empty() {
+                    escape_key(k)
+                } else {
+                    format!("{}.{}", path, escape_key(k))
+                };
+                flatten_into(v, key, ret);
+            }
+        }
+        v => ret.push((path, v.clone())),
+    }
+}
+
+fn escape_key(key: &str) -> String {
+    let mut ret = String::with_capacity(key.len());
+    for c in key.chars() {
+        if matches!(c, '.' | '[' | ']' | '\\') {
+            ret.push('\\');
+        }
+        ret.push(c);
+    }
+    ret
+}
+
+// the inverse of `flatten`: rebuilds the tree from `address.city` / `marks[0]` style keys
+pub fn unflatten(pairs: impl IntoIterator<Item = (String, JsonValue)>) -> Result<JsonValue> {
+    let mut root = PathNode::Empty;
+    for (path, value) in pairs {
+        let segments = parse_path(&path)?;
+        insert_path(&mut root, &segments, value, &path)?;
+    }
+    root.into_value()
+}
+
+enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+// intermediate tree, arrays stay sparse until every index has been seen
+enum PathNode {
+    Empty,
+    Leaf(JsonValue),
+    Object(BTreeMap<String, PathNode>),
+    Array(BTreeMap<usize, PathNode>),
+}
+
+fn parse_path(path: &str) -> Result<Vec<PathSegment>> {
+    let mut ret = Vec::new();
+    if path.is_empty() {
+        return Ok(ret);
+    }
+    let malformed = || anyhow!("invalid path {:?}: malformed index", path);
+    let mut chars = path.chars().peekable();
+    loop {
+        let mut key = String::new();
+        while let Some(c) = chars.next_if(|c| !matches!(c, '.' | '[' | ']')) {
+            if c == '\\' {
+                let c = chars
+                    .next()
+                    .ok_or_else(|| anyhow!("invalid path {:?}: dangling escape", path))?;
+                key.push(c);
+            } else {
+                key.push(c);
+            }
+        }
+        // only the root may be indexed without a key, e.g. `[0].a`
+        if !key.is_empty() {
+            ret.push(PathSegment::Key(key));
+        } else if !ret.is_empty() || chars.peek() != Some(&'[') {
+            return Err(anyhow!("invalid path {:?}: empty key", path));
+        }
+        while chars.next_if_eq(&'[').is_some() {
+            let mut index = String::new();
+            loop {
+                match chars.next() {
+                    Some(']') => break,
+                    Some(c) => index.push(c),
+                    None => return Err(malformed()),
+                }
+            }
+            ret.push(PathSegment::Index(index.parse().map_err(|_| malformed())?));
+        }
+        match chars.next() {
+            None => return Ok(ret),
+            Some('.') => {}
+            Some(_) => return Err(malformed()),
+        }
+    }
+}
+
+fn insert_path(
+    node: &mut PathNode,
+    segments: &[PathSegment],
+    value: JsonValue,
+    path: &str,
+) -> Result<()> {
+    let Some((segment, rest)) = segments.split_first() else {
+        if !matches!(node, PathNode::Empty) {
+            return Err(anyhow!("conflicting path {:?}: already has a value", path));
+        }
+        *node = PathNode::Leaf(value);
+        return Ok(());
+    };
+    if matches!(node, PathNode::Empty) {
+        *node = match segment {
+            PathSegment::Key(_) => PathNode::Object(BTreeMap::new()),
+            PathSegment::Index(_) => PathNode::Array(BTreeMap::new()),
+        };
+    }
+    let child = match (node, segment) {
+        (PathNode::Object(map), PathSegment::Key(key)) => {
+            map.entry(key.clone()).or_insert(PathNode::Empty)
+        }
+        (PathNode::Array(map), PathSegment::Index(index)) => {
+            map.entry(*index).or_insert(PathNode::Empty)
+        }
+        _ => return Err(anyhow!("conflicting path {:?}: type mismatch", path)),
+    };
+    insert_path(child, rest, value, path)
+}
+
+impl PathNode {
+    fn into_value(self) -> Result<JsonValue> {
+        match self {
+            PathNode::Empty => Err(anyhow!("no entries to unflatten")),
+            PathNode::Leaf(v) => Ok(v),
+            PathNode::Object(map) => map
+                .into_iter()
+                .map(|(k, v)| Ok((k, v.into_value()?)))
+                .collect::<Result<BTreeMap<_, _>>>()
+                .map(JsonValue::Object),
+            PathNode::Array(map) => map
+                .into_iter()
+                .enumerate()
+                .map(|(i, (index, v))| {
+                    if i != index {
+                        return Err(anyhow!("missing array index {}", i));
+                    }
+                    v.into_value()
+                })
+                .collect::<Result<Vec<_>>>()
+                .map(JsonValue::Array),
+        }
+    }
+}
+
+// same grammar as `parse_json`, but every node keeps its source span
+pub fn parse_json_spanned(input: &[u8], opts: &ParseOptions) -> Result<Spanned<SpannedValue>> {
+    parse_json_into(input, opts)
+}
+
+// the document built with your own `ValueBuilder`, with the spans filled in
+pub fn parse_json_into<B: ValueBuilder>(input: &[u8], opts: &ParseOptions) -> Result<B> {
+    parse_document(&mut Located::new(input), opts, Location::location)
+}
+
+impl ParseStats {
+    fn collect(&mut self, v: &JsonValue, depth: usize) {
+        match v {
+            JsonValue::Null => self.nulls += 1,
+            JsonValue::Bool(_) => self.booleans += 1,
+            JsonValue::Number(_) => self.numbers += 1,
+            JsonValue::String(_) => self.strings += 1,
+            JsonValue::Array(values) => {
+                self.arrays += 1;
+                self.max_depth = self.max_depth.max(depth + 1);
+                for v in values {
+                    self.collect(v, depth + 1);
+                }
+            }
+            JsonValue::Object(map) => {
+                self.objects += 1;
+                self.max_depth = self.max_depth.max(depth + 1);
+                for v in map.values() {
+                    self.collect(v, depth + 1);
+                }
+            }
+        }
+    }
+}
+
+// yields the elements of a top-level array one at a time instead of building the whole Vec
+pub fn parse_array_stream<'a>(
+    input: &'a [u8],
+    opts: &ParseOptions,
+) -> impl Iterator<Item = Result<JsonValue>> + 'a {
+    let opts = opts.clone();
+    let mut rest = Located::new(input);
+    let mut started = false;
+    let mut done = false;
+    core::iter::from_fn(move || {
+        if done {
+            return None;
+        }
+        match next_array_element(&mut rest, &opts, &mut started) {
+            Ok(Some(v)) => Some(Ok(v)),
+            Ok(None) => {
+                done = true;
+                None
+            }
+            Err(e) => {
+                done = true;
+                Some(Err(e))
+            }
+        }
+    })
+}
+
+fn next_array_element(
+    input: &mut Located<&[u8]>,
+    opts: &ParseOptions,
+    started: &mut bool,
+) -> Result<Option<JsonValue>> {
+    let ws = |i: &mut Located<&[u8]>| skip_ws(i, opts);
+    let err = |e, input: &Located<&[u8]>| json_error(e, input.location(), input.is_empty());
+    let end = if *started {
+        let sep: PResult<char, ContextError> = delimited(ws, alt((',', ']')), ws).parse_next(input);
+        sep.map_err(|e| err(e, input))? == ']'
+    } else {
+        *started = true;
+        check_utf8(input.as_ref())?;
+        let open: PResult<Option<char>, ContextError> =
+            preceded((opt(BOM), ws, '[', ws), opt(']')).parse_next(input);
+        open.map_err(|e| err(e, input))?.is_some()
+    };
+    if end {
+        skip_ws::<_, ContextError>(input, opts).map_err(|e| err(e, input))?;
+        if !input.is_empty() {
+            return Err(anyhow!(ParseError::TrailingData {
+                offset: input.location()
+            }));
+        }
+        return Ok(None);
+    }
+    // elements are handed out one by one, so each gets its own node budget
+    let v = parse_node(input, opts, 1, &ParseState::default()).map_err(|e| err(e, input))?;
+    Ok(Some(v))
+}
+
+// whitespace separated values, e.g. `{"a":1} [1,2] true`. unlike NDJSON a value may span
+// several lines, each value is a document of its own with its own node budget
+pub fn parse_json_seq(input: &[u8], opts: &ParseOptions) -> Result<Vec<JsonValue>> {
+    check_utf8(input)?;
+    let rest = &mut Located::new(input);
+    let err = |e, rest: &Located<&[u8]>| json_error(e, rest.location(), rest.is_empty());
+    let start: PResult<_, ContextError> =
+        (opt(BOM), |i: &mut Located<&[u8]>| skip_ws(i, opts)).parse_next(rest);
+    start.map_err(|e| err(e, rest))?;
+    let mut ret = Vec::new();
+    while !rest.is_empty() {
+        let v = parse_node(rest, opts, 0, &ParseState::default()).map_err(|e| err(e, rest))?;
+        ret.push(v);
+        let end = rest.location();
+        skip_ws::<_, ContextError>(rest, opts).map_err(|e| err(e, rest))?;
+        // `[1]2` would otherwise be read as two values
+        if !rest.is_empty() && rest.location() == end {
+            return Err(anyhow!(ParseError::UnexpectedChar { offset: end }));
+        }
+    }
+    Ok(ret)
+}
+
+// the lexical tokens of `input` with their byte ranges, whitespace (and comments in relaxed mode)
+// skipped. only the tokens themselves are checked, `]]` is two valid tokens; the first error ends
+// the iteration
+pub fn tokenize<'a>(
+    input: &'a [u8],
+    opts: &ParseOptions,
+) -> impl Iterator<Item = Result<Spanned<JsonToken>>> + 'a {
+    let opts = opts.clone();
+    let mut rest = Located::new(input);
+    let mut started = false;
+    let mut done = false;
+    core::iter::from_fn(move || {
+        if done {
+            return None;
+        }
+        match next_token(&mut rest, &opts, &mut started) {
+            Ok(Some(token)) => Some(Ok(token)),
+            Ok(None) => {
+                done = true;
+                None
+            }
+            Err(e) => {
+                done = true;
+                Some(Err(e))
+            }
+        }
+    })
+}
+
+fn next_token(
+    input: &mut Located<&[u8]>,
+    opts: &ParseOptions,
+    started: &mut bool,
+) -> Result<Option<Spanned<JsonToken>>> {
+    let err = |e, input: &Located<&[u8]>| json_error(e, input.location(), input.is_empty());
+    if !*started {
+        *started = true;
+        check_utf8(input.as_ref())?;
+        let bom: PResult<_, ContextError> = opt(BOM).parse_next(input);
+        bom.map_err(|e| err(e, input))?;
+    }
+    skip_ws::<_, ContextError>(input, opts).map_err(|e| err(e, input))?;
+    if input.is_empty() {
+        return Ok(None);
+    }
+    let start = input.location();
+    let token: PResult<JsonToken, ContextError> = alt((
+        '{'.value(JsonToken::BeginObject),
+        '}'.value(JsonToken::EndObject),
+        '['.value(JsonToken::BeginArray),
+        ']'.value(JsonToken::EndArray),
+        ':'.value(JsonToken::Colon),
+        ','.value(JsonToken::Comma),
+        |i: &mut Located<&[u8]>| parse_string(i, opts).map(JsonToken::String),
+        |i: &mut Located<&[u8]>| parse_number(i, opts).map(JsonToken::Number),
+        |i: &mut Located<&[u8]>| parse_bool(i, opts).map(JsonToken::Bool),
+        |i: &mut Located<&[u8]>| parse_null(i, opts).map(|()| JsonToken::Null),
+    ))
+    .parse_next(input);
+    let value = token.map_err(|e| err(e, input))?;
+    Ok(Some(Spanned {
+        value,
+        span: start..input.location(),
+    }))
+}
+
+fn check_utf8(input: &[u8]) -> Result<()> {
+    core::str::from_utf8(input).map_err(|e| {
+        anyhow!(ParseError::InvalidUtf8 {
+            offset: e.valid_up_to()
+        })
+    })?;
+    Ok(())
+}
+
+// the parsers label the failures worth telling apart, anything else is reported where the
+// input stopped
+fn json_error(e: ErrMode<ContextError>, offset: usize, eof: bool) -> anyhow::Error {
+    let label = e.into_inner().and_then(|e| {
+        e.context().find_map(|c| match c {
+            StrContext::Label(l) => Some(*l),
+            _ => None,
+        })
+    });
+    anyhow!(match label {
+        Some(UNTERMINATED_STRING) => ParseError::UnterminatedString { offset },
+        Some(CONTROL_CHAR) => ParseError::UnescapedControlChar { offset },
+        Some(INVALID_ESCAPE) => ParseError::InvalidEscape { offset },
+        Some(INVALID_UTF8) => ParseError::InvalidUtf8 { offset },
+        Some(INVALID_NUMBER) => ParseError::InvalidNumber { offset },
+        Some(DEPTH_EXCEEDED) => ParseError::DepthExceeded { offset },
+        Some(ELEMENT_LIMIT) => ParseError::ElementLimitExceeded { offset },
+        _ if eof => ParseError::UnexpectedEof { offset },
+        _ => ParseError::UnexpectedChar { offset },
+    })
+}
+
+const UNTERMINATED_STRING: &str = "unterminated string";
+const CONTROL_CHAR: &str = "control character";
+const INVALID_ESCAPE: &str = "escape";
+const INVALID_UTF8: &str = "utf-8";
+const INVALID_NUMBER: &str = "number";
+const DEPTH_EXCEEDED: &str = "depth";
+const ELEMENT_LIMIT: &str = "element limit";
+
+fn labelled<Input, Error>(input: &mut Input, label: &'static str) -> ErrMode<Error>
+where
+    Input: Stream,
+    Error: ParserError<Input> + AddContext<Input, StrContext>,
+{
+    let start = input.checkpoint();
+    ErrMode::Cut(
+        Error::from_error_kind(input, ErrorKind::Verify).add_context(
+            input,
+            &start,
+            StrContext::Label(label),
+        ),
+    )
+}
+
+fn parse_null<Input, Error>(input: &mut Input, opts: &ParseOptions) -> PResult<(), Error>
+where
+    Input: StreamIsPartial + Stream + Compare<&'static str> + Compare<Caseless<&'static str>>,
+    Error: ParserError<Input>,
+{
+    if opts.relaxed {
+        Caseless("null").value(()).parse_next(input)
+    } else {
+        "null".value(()).parse_next(input)
+    }
+}
+
+fn parse_bool<Input, Error>(input: &mut Input, opts: &ParseOptions) -> PResult<bool, Error>
+where
+    Input: StreamIsPartial + Stream + Compare<&'static str> + Compare<Caseless<&'static str>>,
+    <Input as Stream>::Slice: ParseSlice<bool>,
+    Error: ParserError<Input>,
+{
+    if opts.relaxed {
+        alt((Caseless("true").value(true), Caseless("false").value(false))).parse_next(input)
+    } else {
+        alt(("true", "false")).parse_to().parse_next(input)
+    }
+}
+
+fn parse_number<Input, Error>(input: &mut Input, opts: &ParseOptions) -> PResult<Num, Error>
+where
+    Input: StreamIsPartial
+        + Stream
+        + Compare<&'static str>
+        + Compare<Caseless<&'static str>>
+        + Compare<char>
+        + AsBStr,
+    <Input as Stream>::Token: AsChar + Clone,
+    <Input as Stream>::IterOffsets: Clone,
+    <Input as Stream>::Slice: ParseSlice<f64> + ParseSlice<i64> + ParseSlice<String> + AsBStr,
+    Error: ParserError<Input> + AddContext<Input, StrContext>,
+{
+    let start = input.checkpoint();
+    let invalid = |input: &mut Input| {
+        input.reset(&start);
+        labelled(input, INVALID_NUMBER)
+    };
+    // relaxed mode also takes a `+` sign, `_` between digits and leading zeros, `+001_000`
+    let minus = opt('-').parse_next(input)?.is_some();
+    let plus = !minus && opts.relaxed && opt('+').parse_next(input)?.is_some();
+    let sign = minus || plus;
+    let groups = |input: &mut Input| -> PResult<bool, Error> {
+        let mut found = false;
+        while opts.relaxed && opt(('_', digit1::<_, Error>)).parse_next(input)?.is_some() {
+            found = true;
+        }
+        Ok(found)
+    };
+    let leading_zero = match digit1::<_, Error>.parse_next(input) {
+        Ok(int) => int.as_bstr().len() > 1 && int.as_bstr()[0] == b'0',
+        // without a sign it's just not a number, let the caller try something else
+        Err(ErrMode::Backtrack(e)) if !sign => return Err(ErrMode::Backtrack(e)),
+        // partial input ran out, more digits may follow
+        Err(e @ ErrMode::Incomplete(_)) => return Err(e),
+        Err(_) => return Err(invalid(input)),
+    };
+    // RFC 8259 has no leading zeros, `01` and `-01`
+    if leading_zero && !opts.relaxed {
+        return Err(invalid(input));
+    }
+    let mut respell = groups(input)? || plus || leading_zero;
+    let is_float = opt('.').parse_next(input)?.is_some();
+    if is_float {
+        match digit1::<_, Error>.void().parse_next(input) {
+            Ok(()) => {}
+            Err(e @ ErrMode::Incomplete(_)) => return Err(e),
+            Err(_) => return Err(invalid(input)),
+        }
+        respell |= groups(input)?;
+    }
+    let is_float = if opt(one_of(['e', 'E'])).parse_next(input)?.is_some() {
+        opt(one_of(['+', '-'])).parse_next(input)?;
+        match digit1::<_, Error>.void().parse_next(input) {
+            Ok(()) => {}
+            Err(e @ ErrMode::Incomplete(_)) => return Err(e),
+            Err(_) => return Err(invalid(input)),
+        }
+        true
+    } else {
+        is_float
+    };
+    // convert the whole `-?digits(.digits)?(e[+-]?digits)?` text at once so the sign is part of
+    // the range check (`i64::MIN` fits) and leading zeros of the fraction are kept
+    let len = input.offset_from(&start);
+    input.reset(&start);
+    let text = input.next_slice(len);
+    let num = if opts.raw_numbers {
+        // even raw text is written back as JSON, so relaxed-only spellings are made strict
+        let text: Option<String> = text.parse_slice();
+        text.map(|t| Num::Raw(if respell { strict_spelling(&t) } else { t }))
+    } else if opts.lossless_numbers {
+        let text: Option<String> = text.parse_slice();
+        text.map(|t| {
+            // compared against the strict spelling, a relaxed `+42` is just `42` and converted
+            let t = if respell { strict_spelling(&t) } else { t };
+            match convert_number(t.as_str(), is_float) {
+                Some(num) if format!("{}", JsonValue::Number(num.clone())) == t => num,
+                _ => Num::Raw(t),
+            }
+        })
+    } else if respell {
+        let text: Option<String> = text.parse_slice();
+        text.and_then(|t| convert_number(strict_spelling(&t).as_str(), is_float))
+    } else {
+        convert_number(text, is_float)
+    };
+    num.ok_or_else(|| invalid(input))
+}
+
+// a relaxed number spelled as RFC 8259 wants it: `+001_000.5` is `1000.5`
+fn strict_spelling(text: &str) -> String {
+    let text = text.strip_prefix('+').unwrap_or(text);
+    let (sign, text) = match text.strip_prefix('-') {
+        Some(text) => ("-", text),
+        None => ("", text),
+    };
+    let digits: String = text.chars().filter(|c| *c != '_').collect();
+    let int_len = digits
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(digits.len());
+    // one zero stays, `00.5` is `0.5`
+    let zeros = digits[..int_len - 1]
+        .bytes()
+        .take_while(|b| *b == b'0')
+        .count();
+    format!("{}{}", sign, &digits[zeros..])
+}
+
+fn convert_number<S: ParseSlice<f64> + ParseSlice<i64>>(text: S, is_float: bool) -> Option<Num> {
+    if is_float {
+        text.parse_slice()
+            .filter(|f: &f64| f.is_finite())
+            .map(Num::Float)
+    } else {
+        text.parse_slice().map(Num::Int)
+    }
+}
+
+fn parse_string<Input, Error>(input: &mut Input, opts: &ParseOptions) -> PResult<String, Error>
+where
+    Input: StreamIsPartial
+        + Stream
+        + Compare<&'static str>
+        + Compare<Caseless<&'static str>>
+        + Compare<char>
+        + AsBStr
+        + FindSlice<char>,
+    <Input as Stream>::Token: AsChar + Clone,
+    <Input as Stream>::IterOffsets: Clone,
+    <Input as Stream>::Slice: ParseSlice<String> + AsBStr,
+    Error: ParserError<Input> + AddContext<Input, StrContext>,
+{
+    let open = input.checkpoint();
+    let quote = if opts.relaxed {
+        alt(('"', '\'')).parse_next(input)?.as_char()
+    } else {
+        '"'.parse_next(input)?.as_char()
+    };
+    let mut ret = String::new();
+    loop {
+        let start = input.checkpoint();
+        let chunk = take_till(0.., [quote, '\\']).parse_next(input)?;
+        // RFC 8259 requires control characters to be escaped
+        let bad = if opts.relaxed {
+            None
+        } else {
+            chunk.as_bstr().iter().position(|b| *b < 0x20)
+        };
+        if let Some(bad) = bad {
+            input.reset(&start);
+            input.next_slice(bad);
+            return Err(labelled(input, CONTROL_CHAR));
+        }
+        let Some(chunk): Option<String> = chunk.parse_slice() else {
+            // documents are validated up front, but a prefix is only checked once it's parsed
+            let valid =
+                core::str::from_utf8(chunk.as_bstr()).map_or_else(|e| e.valid_up_to(), str::len);
+            input.reset(&start);
+            input.next_slice(valid);
+            return Err(labelled(input, INVALID_UTF8));
+        };
+        ret.push_str(&chunk);
+        let backslash = input.checkpoint();
+        let Ok(end) = any::<_, Error>.parse_next(input) else {
+            input.reset(&open);
+            return Err(labelled(input, UNTERMINATED_STRING));
+        };
+        if end.as_char() == quote {
+            break;
+        }
+        let escaped = opt(alt((
+            quote.value(quote),
+            '"'.value('"'),
+            '\\'.value('\\'),
+            'b'.value('\u{8}'),
+            'f'.value('\u{c}'),
+            'n'.value('\n'),
+            'r'.value('\r'),
+            't'.value('\t'),
+            '/'.value('/'),
+        )))
+        .parse_next(input)?;
+        let escaped = match escaped {
+            Some(c) => Some(c),
+            None if opt('u').parse_next(input)?.is_some() => parse_unicode_escape(input)?,
+            // relaxed mode keeps an unknown escape as is, RFC 8259 has no others
+            None if opts.relaxed => Some('\\'),
+            None => None,
+        };
+        let Some(escaped) = escaped else {
+            input.reset(&backslash);
+            return Err(labelled(input, INVALID_ESCAPE));
+        };
+        ret.push(escaped);
+    }
+    Ok(ret)
+}
+
+// the 4 hex digits after `\u`, a UTF-16 surrogate pair is two escapes in a row, `\ud83d\ude00`.
+// `None` for bad digits or a lone surrogate
+fn parse_unicode_escape<Input, Error>(input: &mut Input) -> PResult<Option<char>, Error>
+where
+    Input: StreamIsPartial + Stream + Compare<&'static str> + AsBStr,
+    <Input as Stream>::Token: AsChar,
+    <Input as Stream>::Slice: AsBStr,
+    Error: ParserError<Input>,
+{
+    let hex = |input: &mut Input| -> PResult<Option<u32>, Error> {
+        let digits = opt(take_while(4, AsChar::is_hex_digit)).parse_next(input)?;
+        Ok(digits.map(|d| {
+            d.as_bstr()
+                .iter()
+                .fold(0, |n, b| n * 16 + (*b as char).to_digit(16).unwrap_or(0))
+        }))
+    };
+    let Some(unit) = hex(input)? else {
+        return Ok(None);
+    };
+    let unit = match unit {
+        0xd800..=0xdbff => {
+            if opt("\\u").parse_next(input)?.is_none() {
+                return Ok(None);
+            }
+            match hex(input)? {
+                Some(low @ 0xdc00..=0xdfff) => 0x10000 + ((unit - 0xd800) << 10) + (low - 0xdc00),
+                _ => return Ok(None),
+            }
+        }
+        unit => unit,
+    };
+    // `None` for a lone low surrogate too
+    Ok(char::from_u32(unit))
+}
+
+// the only place inter-token whitespace is skipped; relaxed mode also skips `//` line
+// comments and `/* */` block comments
+pub fn skip_ws<Input, Error>(input: &mut Input, opts: &ParseOptions) -> PResult<(), Error>
+where
+    Input: StreamIsPartial + Stream + Compare<&'static str> + Compare<char>,
+    <Input as Stream>::Token: AsChar + Clone,
+    Error: ParserError<Input>,
+{
+    multispace0.parse_next(input)?;
+    if !opts.relaxed {
+        return Ok(());
+    }
+    loop {
+        if opt("//").parse_next(input)?.is_some() {
+            take_till(0.., '\n').parse_next(input)?;
+        } else if opt("/*").parse_next(input)?.is_some() {
+            // an unterminated block comment can't be anything else
+            loop {
+                take_till(0.., '*').parse_next(input)?;
+                any.parse_next(input).map_err(ErrMode::cut)?;
+                if opt('/').parse_next(input)?.is_some() {
+                    break;
+                }
+            }
+        } else {
+            return Ok(());
+        }
+        multispace0.parse_next(input)?;
+    }
+}
+
+pub fn sep_with_space<'a, Input, Output, Error, ParseNext>(
+    opts: &'a ParseOptions,
+    mut parser: ParseNext,
+) -> impl Parser<Input, (), Error> + 'a
+where
+    Input: StreamIsPartial + Stream + Compare<&'static str> + Compare<char> + 'a,
+    <Input as Stream>::Token: AsChar + Clone,
+    Error: ParserError<Input> + 'a,
+    Output: 'a,
+    ParseNext: Parser<Input, Output, Error> + 'a,
+{
+    trace("sep_with_space", move |input: &mut Input| {
+        skip_ws(input, opts)?;
+        parser.parse_next(input)?;
+        skip_ws(input, opts)?;
+        Ok(())
+    })
+}
+
+// where the input is in the document, only `Located` input keeps track of it; plain slices
+// report 0 so building a `JsonValue` doesn't pay for spans it throws away
+pub trait Position {
+    fn position(&self) -> usize;
+}
+
+impl<I> Position for Located<I>
+where
+    I: Clone + Offset,
+{
+    fn position(&self) -> usize {
+        self.location()
+    }
+}
+
+impl Position for &[u8] {
+    fn position(&self) -> usize {
+        0
+    }
+}
+
+impl Position for &str {
+    fn position(&self) -> usize {
+        0
+    }
+}
+
+impl Position for &BStr {
+    fn position(&self) -> usize {
+        0
+    }
+}
+
+impl<I: Position> Position for Partial<I> {
+    fn position(&self) -> usize {
+        (**self).position()
+    }
+}
+
+// what the grammar builds a document into, so one parser yields the plain tree, the spanned
+// tree or a model of your own. a value is built once its children are; `span` is its byte range
+// for `Located` input and `0..0` for a plain slice
+pub trait ValueBuilder: Sized {
+    // an array's elements and an object's members while they are being parsed
+    type Elements: Default;
+    type Members: Default;
+
+    fn build_null(span: Range<usize>) -> Self;
+    fn build_bool(b: bool, span: Range<usize>) -> Self;
+    fn build_number(n: Num, span: Range<usize>) -> Self;
+    fn build_string(s: String, span: Range<usize>) -> Self;
+    fn push_array_elem(array: &mut Self::Elements, value: Self);
+    // called again for a repeated key, the built-in trees keep the last value
+    fn insert_object_key(object: &mut Self::Members, key: String, value: Self);
+    fn build_array(array: Self::Elements, span: Range<usize>) -> Self;
+    fn build_object(object: Self::Members, span: Range<usize>) -> Self;
+}
+
+impl ValueBuilder for JsonValue {
+    type Elements = Vec<JsonValue>;
+    type Members = BTreeMap<String, JsonValue>;
+
+    #[inline(always)]
+    fn build_null(_span: Range<usize>) -> Self {
+        JsonValue::Null
+    }
+
+    #[inline(always)]
+    fn build_bool(b: bool, _span: Range<usize>) -> Self {
+        JsonValue::Bool(b)
+    }
+
+    #[inline(always)]
+    fn build_number(n: Num, _span: Range<usize>) -> Self {
+        JsonValue::Number(n)
+    }
+
+    #[inline(always)]
+    fn build_string(s: String, _span: Range<usize>) -> Self {
+        JsonValue::String(s)
+    }
+
+    #[inline(always)]
+    fn push_array_elem(array: &mut Self::Elements, value: Self) {
+        array.push(value);
+    }
+
+    #[inline(always)]
+    fn insert_object_key(object: &mut Self::Members, key: String, value: Self) {
+        object.insert(key, value);
+    }
+
+    #[inline(always)]
+    fn build_array(array: Self::Elements, _span: Range<usize>) -> Self {
+        JsonValue::Array(array)
+    }
+
+    #[inline(always)]
+    fn build_object(object: Self::Members, _span: Range<usize>) -> Self {
+        JsonValue::Object(object)
+    }
+}
+
+impl ValueBuilder for Spanned<SpannedValue> {
+    type Elements = Vec<Spanned<SpannedValue>>;
+    type Members = BTreeMap<String, Spanned<SpannedValue>>;
+
+    fn build_null(span: Range<usize>) -> Self {
+        Spanned {
+            value: SpannedValue::Null,
+            span,
+        }
+    }
+
+    fn build_bool(b: bool, span: Range<usize>) -> Self {
+        Spanned {
+            value: SpannedValue::Bool(b),
+            span,
+        }
+    }
+
+    fn build_number(n: Num, span: Range<usize>) -> Self {
+        Spanned {
+            value: SpannedValue::Number(n),
+            span,
+        }
+    }
+
+    fn build_string(s: String, span: Range<usize>) -> Self {
+        Spanned {
+            value: SpannedValue::String(s),
+            span,
+        }
+    }
+
+    fn push_array_elem(array: &mut Self::Elements, value: Self) {
+        array.push(value);
+    }
+
+    fn insert_object_key(object: &mut Self::Members, key: String, value: Self) {
+        object.insert(key, value);
+    }
+
+    fn build_array(array: Self::Elements, span: Range<usize>) -> Self {
+        Spanned {
+            value: SpannedValue::Array(array),
+            span,
+        }
+    }
+
+    fn build_object(object: Self::Members, span: Range<usize>) -> Self {
+        Spanned {
+            value: SpannedValue::Object(object),
+            span,
+        }
+    }
+}
+
+fn parse_array<Input, Error, N>(
+    input: &mut Input,
+    opts: &ParseOptions,
+    depth: usize,
+    state: &ParseState,
+) -> PResult<N::Elements, Error>
+where
+    Input: StreamIsPartial
+        + Stream
+        + Position
+        + Compare<&'static str>
+        + Compare<Caseless<&'static str>>
+        + Compare<char>
+        + AsBStr
+        + FindSlice<char>,
+    <Input as Stream>::Token: AsChar + Clone,
+    <Input as Stream>::IterOffsets: Clone,
+    <Input as Stream>::Slice:
+        ParseSlice<String> + ParseSlice<bool> + ParseSlice<i64> + ParseSlice<f64> + AsBStr,
+    Error: ParserError<Input> + AddContext<Input, StrContext>,
+    N: ValueBuilder,
+{
+    // the closing bracket doesn't swallow the trailing whitespace so a span ends at `]`
+    let sep1 = sep_with_space(opts, '[');
+    let sep2 = (|i: &mut Input| skip_ws(i, opts), ']');
+    let sep_comma = sep_with_space(opts, ',');
+    let elements = Cell::new(0);
+    let parse_values = list(
+        move |i: &mut Input| {
+            counted(i, &elements, opts.max_elements, |i| {
+                parse_node(i, opts, depth + 1, state)
+            })
+        },
+        sep_comma,
+        N::push_array_elem,
+    );
+    // past the bracket nothing else can match, so errors are reported where they happen
+    trace(
+        "parse_array",
+        delimited(
+            (sep1, |i: &mut Input| check_depth(i, opts, depth)),
+            cut_err(parse_values),
+            cut_err(sep2),
+        ),
+    )
+    .parse_next(input)
+}
+
+fn parse_object<Input, Error, N>(
+    input: &mut Input,
+    opts: &ParseOptions,
+    depth: usize,
+    state: &ParseState,
+) -> PResult<N::Members, Error>
+where
+    Input: StreamIsPartial
+        + Stream
+        + Position
+        + Compare<&'static str>
+        + Compare<Caseless<&'static str>>
+        + Compare<char>
+        + AsBStr
+        + FindSlice<char>,
+    <Input as Stream>::Token: AsChar + Clone,
+    <Input as Stream>::IterOffsets: Clone,
+    <Input as Stream>::Slice:
+        ParseSlice<String> + ParseSlice<bool> + ParseSlice<i64> + ParseSlice<f64> + AsBStr,
+    Error: ParserError<Input> + AddContext<Input, StrContext>,
+    N: ValueBuilder,
+{
+    let sep1 = ('{', |i: &mut Input| skip_ws(i, opts));
+    let sep2 = (|i: &mut Input| skip_ws(i, opts), '}');
+    let sep_colon = sep_with_space(opts, ':');
+    let sep_comma = sep_with_space(opts, ',');
+    let elements = Cell::new(0);
+    let mut parse_kv_pair = separated_pair(
+        |i: &mut Input| parse_key(i, opts),
+        sep_colon,
+        |i: &mut Input| parse_node(i, opts, depth + 1, state),
+    );
+    let parse_kv = list(
+        move |i: &mut Input| {
+            counted(i, &elements, opts.max_elements, |i| {
+                parse_kv_pair.parse_next(i)
+            })
+        },
+        sep_comma,
+        |object: &mut N::Members, (key, value)| N::insert_object_key(object, key, value),
+    );
+    trace(
+        "parse_object",
+        delimited(
+            (sep1, |i: &mut Input| check_depth(i, opts, depth)),
+            cut_err(parse_kv),
+            cut_err(sep2),
+        ),
+    )
+    .parse_next(input)
+}
+
+// relaxed mode also takes a bare identifier as a key, `{name: 1}`: letters, digits, `_` and `$`,
+// not starting with a digit
+fn parse_key<Input, Error>(input: &mut Input, opts: &ParseOptions) -> PResult<String, Error>
+where
+    Input: StreamIsPartial
+        + Stream
+        + Compare<&'static str>
+        + Compare<Caseless<&'static str>>
+        + Compare<char>
+        + AsBStr
+        + FindSlice<char>,
+    <Input as Stream>::Token: AsChar + Clone,
+    <Input as Stream>::IterOffsets: Clone,
+    <Input as Stream>::Slice: ParseSlice<String> + AsBStr,
+    Error: ParserError<Input> + AddContext<Input, StrContext>,
+{
+    if !opts.relaxed {
+        return parse_string(input, opts);
+    }
+    let ident_start = |c: <Input as Stream>::Token| {
+        let c = c.as_char();
+        c.is_ascii_alphabetic() || c == '_' || c == '$'
+    };
+    let ident = (
+        one_of(ident_start),
+        take_while(0.., |c: <Input as Stream>::Token| {
+            let c = c.as_char();
+            c.is_ascii_alphanumeric() || c == '_' || c == '$'
+        }),
+    )
+        .take()
+        .map(|key: <Input as Stream>::Slice| key.parse_slice().unwrap_or_default());
+    alt((|i: &mut Input| parse_string(i, opts), ident)).parse_next(input)
+}
+
+// like `separated(0.., item, sep)`, except that an item must follow every separator, so in
+// `[1, }` the error is reported at the `}` rather than at the comma. items are handed to `push`
+fn list<Input, O, C, Error>(
+    mut item: impl Parser<Input, O, Error>,
+    mut sep: impl Parser<Input, (), Error>,
+    push: impl Fn(&mut C, O),
+) -> impl Parser<Input, C, Error>
+where
+    Input: Stream,
+    C: Default,
+    Error: ParserError<Input>,
+{
+    move |input: &mut Input| {
+        let mut ret = C::default();
+        let Some(first) = opt(item.by_ref()).parse_next(input)? else {
+            return Ok(ret);
+        };
+        push(&mut ret, first);
+        while opt(sep.by_ref()).parse_next(input)?.is_some() {
+            push(&mut ret, cut_err(item.by_ref()).parse_next(input)?);
+        }
+        Ok(ret)
+    }
+}
+
+// entering a container at `depth` must not exceed the configured nesting limit
+fn check_depth<Input, Error>(
+    input: &mut Input,
+    opts: &ParseOptions,
+    depth: usize,
+) -> PResult<(), Error>
+where
+    Input: Stream,
+    Error: ParserError<Input> + AddContext<Input, StrContext>,
+{
+    if depth >= opts.max_depth {
+        return Err(labelled(input, DEPTH_EXCEEDED));
+    }
+    Ok(())
+}
+
+// runs `parser` and bumps `count` only once it succeeded, so e.g. `[]` holds no element; going
+// past `max` is reported where the offending item starts
+fn counted<Input, O, Error>(
+    input: &mut Input,
+    count: &Cell<usize>,
+    max: usize,
+    mut parser: impl FnMut(&mut Input) -> PResult<O, Error>,
+) -> PResult<O, Error>
+where
+    Input: Stream,
+    Error: ParserError<Input> + AddContext<Input, StrContext>,
+{
+    let start = input.checkpoint();
+    let value = parser(input)?;
+    count.set(count.get() + 1);
+    if count.get() > max {
+        input.reset(&start);
+        return Err(labelled(input, ELEMENT_LIMIT));
+    }
+    Ok(value)
+}
+
+// running totals for a single document, shared by all of its values
+#[derive(Debug, Default)]
+pub struct ParseState {
+    nodes: Cell<usize>,
+}
+
+// `depth` is the nesting of the value being parsed, 0 for the document root, `state` is shared
+// by every value of the document
+pub fn parse_value<Input, Error>(
+    input: &mut Input,
+    opts: &ParseOptions,
+    depth: usize,
+    state: &ParseState,
+) -> PResult<JsonValue, Error>
+where
+    Input: StreamIsPartial
+        + Stream
+        + Position
+        + Compare<&'static str>
+        + Compare<Caseless<&'static str>>
+        + Compare<char>
+        + AsBStr
+        + FindSlice<char>,
+    <Input as Stream>::Token: AsChar + Clone,
+    <Input as Stream>::IterOffsets: Clone,
+    <Input as Stream>::Slice:
+        ParseSlice<String> + ParseSlice<bool> + ParseSlice<i64> + ParseSlice<f64> + AsBStr,
+    Error: ParserError<Input> + AddContext<Input, StrContext>,
+{
+    parse_node(input, opts, depth, state)
+}
+
+fn parse_node<Input, Error, N>(
+    input: &mut Input,
+    opts: &ParseOptions,
+    depth: usize,
+    state: &ParseState,
+) -> PResult<N, Error>
+where
+    Input: StreamIsPartial
+        + Stream
+        + Position
+        + Compare<&'static str>
+        + Compare<Caseless<&'static str>>
+        + Compare<char>
+        + AsBStr
+        + FindSlice<char>,
+    <Input as Stream>::Token: AsChar + Clone,
+    <Input as Stream>::IterOffsets: Clone,
+    <Input as Stream>::Slice:
+        ParseSlice<String> + ParseSlice<bool> + ParseSlice<i64> + ParseSlice<f64> + AsBStr,
+    Error: ParserError<Input> + AddContext<Input, StrContext>,
+    N: ValueBuilder,
+{
+    counted(input, &state.nodes, opts.max_nodes, |input| {
+        parse_alt(input, opts, depth, state)
+    })
+}
+
+fn parse_alt<Input, Error, N>(
+    input: &mut Input,
+    opts: &ParseOptions,
+    depth: usize,
+    state: &ParseState,
+) -> PResult<N, Error>
+where
+    Input: StreamIsPartial
+        + Stream
+        + Position
+        + Compare<&'static str>
+        + Compare<Caseless<&'static str>>
+        + Compare<char>
+        + AsBStr
+        + FindSlice<char>,
+    <Input as Stream>::Token: AsChar + Clone,
+    <Input as Stream>::IterOffsets: Clone,
+    <Input as Stream>::Slice:
+        ParseSlice<String> + ParseSlice<bool> + ParseSlice<i64> + ParseSlice<f64> + AsBStr,
+    Error: ParserError<Input> + AddContext<Input, StrContext>,
+    N: ValueBuilder,
+{
+    let start = input.position();
+    // build in each branch so the plain tree doesn't pay for an intermediate value
+    trace(
+        "parse_value",
+        alt((
+            trace("null", move |i: &mut Input| {
+                parse_null(i, opts)?;
+                Ok(N::build_null(start..i.position()))
+            }),
+            trace("bool", move |i: &mut Input| {
+                let b = parse_bool(i, opts)?;
+                Ok(N::build_bool(b, start..i.position()))
+            }),
+            trace("number", move |i: &mut Input| {
+                let n = parse_number(i, opts)?;
+                Ok(N::build_number(n, start..i.position()))
+            }),
+            trace("string", move |i: &mut Input| {
+                let s = parse_string(i, opts)?;
+                Ok(N::build_string(s, start..i.position()))
+            }),
+            move |i: &mut Input| {
+                let values = parse_array::<_, _, N>(i, opts, depth, state)?;
+                Ok(N::build_array(values, start..i.position()))
+            },
+            move |i: &mut Input| {
+                let map = parse_object::<_, _, N>(i, opts, depth, state)?;
+                Ok(N::build_object(map, start..i.position()))
+            },
+        )),
+    )
+    .parse_next(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"{
+        "name": "John Doe",
+        "age": 30,
+        "is_student": false,
+        "marks": [90, -80, 85.1],
+        "address": {
+            "city": "New York",
+            "zip": 10001
+        }
+    }"#;
+
+    #[test]
+    fn test_parse_null() -> PResult<(), ContextError> {
+        let input = "null";
+        parse_null(&mut (&*input), &ParseOptions::default())?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_bool() -> PResult<(), ContextError> {
+        let input = "true";
+        let result = parse_bool(&mut (&*input), &ParseOptions::default())?;
+        assert!(result);
+
+        let input = "false";
+        let result = parse_bool(&mut (&*input), &ParseOptions::default())?;
+        assert!(!result);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_caseless_literals() -> Result<()> {
+        let relaxed = ParseOptions {
+            relaxed: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            parse_json_with_opts(b"[TRUE, False, Null]", &relaxed)?,
+            JsonValue::Array(vec![
+                JsonValue::Bool(true),
+                JsonValue::Bool(false),
+                JsonValue::Null
+            ])
+        );
+
+        assert!(parse_json(b"TRUE").is_err());
+        assert!(parse_json(b"Null").is_err());
+        assert_eq!(parse_json(b"true")?, JsonValue::Bool(true));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_number() -> PResult<(), ContextError> {
+        let input = "123.45";
+        let result = parse_number(&mut (&*input), &ParseOptions::default())?;
+        assert_eq!(result, Num::Float(123.45));
+
+        let input = "-123.45";
+        let result = parse_number(&mut (&*input), &ParseOptions::default())?;
+        assert_eq!(result, Num::Float(-123.45));
+
+        // leading zeros of the fraction are significant
+        let cases = [
+            ("1.05", Num::Float(1.05)),
+            ("-0.001", Num::Float(-0.001)),
+            ("-0", Num::Int(0)),
+            (
+                "0.12345678901234567890123",
+                Num::Float(0.123_456_789_012_345_68),
+            ),
+            ("1e3", Num::Float(1000.0)),
+            ("-2.5E-3", Num::Float(-0.0025)),
+            ("1e+21", Num::Float(1e21)),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                parse_number(&mut (&*input), &ParseOptions::default())?,
+                expected
+            );
+        }
+        for input in ["1e", "1e+", "1.5E-", "1e400", "01", "-00", "00.5"] {
+            assert!(parse_json(input.as_bytes()).is_err(), "{}", input);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_relaxed_numbers() -> Result<()> {
+        let opts = ParseOptions {
+            relaxed: true,
+            ..Default::default()
+        };
+        let cases = [
+            ("+42", Num::Int(42)),
+            ("1_000", Num::Int(1000)),
+            ("-1_000_000", Num::Int(-1_000_000)),
+            ("+1_000.000_5", Num::Float(1000.0005)),
+            ("+0.5", Num::Float(0.5)),
+            ("007", Num::Int(7)),
+            ("-01.5", Num::Float(-1.5)),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                parse_json_with_opts(input.as_bytes(), &opts)?,
+                JsonValue::Number(expected),
+                "{}",
+                input
+            );
+        }
+        for input in ["+42", "1_000", "[1_0]", "+0.5", "007"] {
+            assert!(parse_json(input.as_bytes()).is_err(), "{}", input);
+        }
+        for input in ["1__0", "_1", "1_", "1_.5", "1._5", "+-1", "-+1", "+_1"] {
+            assert!(
+                parse_json_with_opts(input.as_bytes(), &opts).is_err(),
+                "{}",
+                input
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_raw_numbers() -> Result<()> {
+        let opts = ParseOptions {
+            raw_numbers: true,
+            ..Default::default()
+        };
+        let v = parse_json_with_opts(b"[0.1, 12345678901234567890.0001, -0, 7]", &opts)?;
+        let raw: Vec<_> = v
+            .elements()
+            .map(|v| match v {
+                JsonValue::Number(Num::Raw(s)) => s.as_str(),
+                v => panic!("expected a raw number, got {:?}", v),
+            })
+            .collect();
+        assert_eq!(raw, ["0.1", "12345678901234567890.0001", "-0", "7"]);
+        // still validated
+        assert!(parse_json_with_opts(b"1.", &opts).is_err());
+
+        // relaxed-only spellings come out as strict JSON
+        let relaxed = ParseOptions {
+            relaxed: true,
+            ..opts.clone()
+        };
+        let v = parse_json_with_opts(b"[+1, 1_0, -00_7.5_0, +0, 0.0_1, 1e+3]", &relaxed)?;
+        assert_eq!(v.to_string(), "[1,10,-7.50,0,0.01,1e+3]");
+        assert_eq!(
+            parse_json(v.to_string().as_bytes())?.to_string(),
+            "[1,10,-7.5,0,0.01,1000.0]"
+        );
+
+        let v = parse_json_with_opts(br#"{"n": 7, "f": 2.5}"#, &opts)?;
+        assert_eq!(v.get_as::<i64>("n")?, 7);
+        assert_eq!(v.get_as::<f64>("f")?, 2.5);
+        assert!(v.get_as::<i64>("f").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_lossless_numbers() -> Result<()> {
+        let opts = ParseOptions {
+            lossless_numbers: true,
+            ..Default::default()
+        };
+        for input in ["1.0", "1e3", "1.50"] {
+            let v = parse_json_with_opts(input.as_bytes(), &opts)?;
+            assert_eq!(v.to_string(), input);
+        }
+        // all of these get rewritten by the default conversion, or don't parse at all
+        for input in ["1e3", "1.50", "-0", "1E+3", "0.10", "99999999999999999999"] {
+            let v = parse_json_with_opts(input.as_bytes(), &opts)?;
+            assert_eq!(v, JsonValue::Number(Num::Raw(input.to_string())));
+            assert_eq!(v.to_string(), input);
+            let default = parse_json(input.as_bytes()).map(|v| v.to_string());
+            assert_ne!(default.ok().as_deref(), Some(input));
+        }
+        let input = r#"{"a":[1.0,1e3,1.50,1,85.1,-2.5e-7],"b":-0}"#;
+        let v = parse_json_with_opts(input.as_bytes(), &opts)?;
+        assert_eq!(v.to_string(), input);
+
+        // numbers that come back unchanged are converted, only the others stay text
+        let a: Vec<_> = v.get("a").into_iter().flat_map(|a| a.elements()).collect();
+        assert_eq!(a[0], &JsonValue::float(1.0));
+        assert_eq!(a[1], &JsonValue::Number(Num::Raw("1e3".to_string())));
+        assert_eq!(a[2], &JsonValue::Number(Num::Raw("1.50".to_string())));
+        assert_eq!(a[3], &JsonValue::int(1));
+        assert_eq!(a[4], &JsonValue::float(85.1));
+        assert_eq!(a[1].clone().into_f64(), Ok(1000.0));
+        assert_eq!(v.get_as::<i64>("b")?, 0);
+
+        // relaxed spellings are written back as strict JSON and only stay raw where the
+        // strict text itself wouldn't survive a conversion
+        let relaxed = ParseOptions {
+            relaxed: true,
+            ..opts.clone()
+        };
+        let v = parse_json_with_opts(
+            b"[+42, 1_000, +1.50, 007, -0_0.5, 1_0e2, 99_999_999_999_999_999_999]",
+            &relaxed,
+        )?;
+        let s = v.to_string();
+        assert_eq!(s, "[42,1000,1.50,7,-0.5,10e2,99999999999999999999]");
+        assert_eq!(parse_json_with_opts(s.as_bytes(), &opts)?, v);
+        let a: Vec<_> = v.elements().collect();
+        assert_eq!(a[0], &JsonValue::int(42));
+        assert_eq!(a[1], &JsonValue::int(1000));
+        assert_eq!(a[3], &JsonValue::int(7));
+        assert_eq!(a[4], &JsonValue::float(-0.5));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_number_overflow() {
+        let input = "9223372036854775808";
+        let result: PResult<Num, ContextError> =
+            parse_number(&mut (&*input), &ParseOptions::default());
+        assert!(matches!(result, Err(ErrMode::Cut(_))));
+
+        assert!(parse_json(b"[1, 99999999999999999999]").is_err());
+        assert_eq!(
+            parse_json(b"-9223372036854775807").unwrap(),
+            JsonValue::Number(Num::Int(-9223372036854775807))
+        );
+        assert_eq!(
+            parse_json(b"-9223372036854775808").unwrap(),
+            JsonValue::Number(Num::Int(i64::MIN))
+        );
+        assert!(parse_json(b"-9223372036854775809").is_err());
+    }
+
+    #[test]
+    fn test_parse_string() -> PResult<(), ContextError> {
+        let input = r#""Hello, World!""#;
+        let result = parse_string(&mut (&*input), &ParseOptions::default())?;
+        assert_eq!(result, "Hello, World!");
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_single_quoted_string() -> PResult<(), ContextError> {
+        let opts = ParseOptions {
+            relaxed: true,
+            ..Default::default()
+        };
+        let input = r#"'it\'s "fine"'"#;
+        let result = parse_string(&mut (&*input), &opts)?;
+        assert_eq!(result, r#"it's "fine""#);
+
+        let input = r#""Hello, World!""#;
+        let result = parse_string(&mut (&*input), &opts)?;
+        assert_eq!(result, "Hello, World!");
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_single_quoted_string_strict() {
+        let input = "'hello'";
+        let result: PResult<String, ContextError> =
+            parse_string(&mut (&*input), &ParseOptions::default());
+        assert!(result.is_err());
+        assert!(parse_json(b"['hello']").is_err());
+        assert!(parse_json_with_opts(
+            b"['hello']",
+            &ParseOptions {
+                relaxed: true,
+                ..Default::default()
+            }
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_parse_json_surrounding_whitespace() -> Result<()> {
+        assert_eq!(parse_json(b"  null  ")?, JsonValue::Null);
+        assert_eq!(
+            parse_json(b"\n\t[1, 2]\n")?,
+            JsonValue::Array(vec![
+                JsonValue::Number(Num::Int(1)),
+                JsonValue::Number(Num::Int(2))
+            ])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_json_empty_input() {
+        for input in ["", "   ", "\n\t"] {
+            let err = parse_json(input.as_bytes()).unwrap_err();
+            assert_eq!(
+                err.downcast_ref::<ParseError>(),
+                Some(&ParseError::EmptyInput {
+                    offset: input.len()
+                })
+            );
+            assert_eq!(err.to_string(), "empty input: expected a JSON value");
+        }
+        assert!(parse_json_spanned(b"\xEF\xBB\xBF ", &ParseOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_parse_json_trailing_data() {
+        let err = parse_json(b"null null").unwrap_err();
+        assert_eq!(err.to_string(), "trailing data at byte 5");
+    }
+
+    #[test]
+    fn test_parse_json_invalid_utf8() {
+        let err = parse_json(b"[\"a\xc3\x28\"]").unwrap_err();
+        assert_eq!(err.to_string(), "invalid UTF-8 at byte 3");
+    }
+
+    #[test]
+    fn test_parse_json_prefix() -> Result<()> {
+        let input = br#"{"a":1}rest"#;
+        let (v, len) = parse_json_prefix(input, &ParseOptions::default())?;
+        let mut expected = BTreeMap::new();
+        expected.insert("a".to_string(), JsonValue::Number(Num::Int(1)));
+        assert_eq!(v, JsonValue::Object(expected));
+        assert_eq!(len, 7);
+        assert_eq!(&input[len..], b"rest");
+
+        let err = parse_json_prefix(b"\"\xff\"", &ParseOptions::default()).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ParseError>(),
+            Some(&ParseError::InvalidUtf8 { offset: 1 })
+        );
+        // invalid bytes after the value are the caller's business
+        assert_eq!(parse_json_prefix(b"1 \xff", &ParseOptions::default())?.1, 1);
+
+        let relaxed = ParseOptions {
+            relaxed: true,
+            ..Default::default()
+        };
+        assert_eq!(parse_json_prefix(b"/* x */ 'a' rest", &relaxed)?.1, 11);
+        let err = parse_json_prefix(b"/* \xff */ 1", &relaxed).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ParseError>(),
+            Some(&ParseError::InvalidUtf8 { offset: 3 })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_json_max_depth() -> Result<()> {
+        let input = format!("{}{}", "[".repeat(100_000), "]".repeat(100_000));
+        assert!(parse_json(input.as_bytes()).is_err());
+
+        let input = format!("{}{}", "[".repeat(128), "]".repeat(128));
+        assert!(parse_json(input.as_bytes()).is_ok());
+
+        let opts = ParseOptions {
+            max_depth: 2,
+            ..Default::default()
+        };
+        assert!(parse_json_with_opts(br#"{"a": [1]}"#, &opts).is_ok());
+        assert!(parse_json_with_opts(br#"{"a": [[1]]}"#, &opts).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_string_control_characters() -> Result<()> {
+        let err = parse_json(b"\"a\tb\"").unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ParseError>(),
+            Some(&ParseError::UnescapedControlChar { offset: 2 })
+        );
+        assert_eq!(err.to_string(), "unescaped control character at byte 2");
+        assert!(parse_json(b"[\"line\nbreak\"]").is_err());
+
+        assert_eq!(
+            parse_json(br#""a\tb\n""#)?,
+            JsonValue::String("a\tb\n".to_string())
+        );
+
+        let relaxed = ParseOptions {
+            relaxed: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            parse_json_with_opts(b"\"a\tb\"", &relaxed)?,
+            JsonValue::String("a\tb".to_string())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_string_escapes() -> Result<()> {
+        let cases = [
+            (r#""\u0041""#, "A"),
+            (r#""\u00e9\u00E9""#, "éé"),
+            (r#""\ud83d\ude00""#, "😀"),
+            (r#""a\u0001b""#, "a\u{1}b"),
+            (r#""\"\\\/\b\f\n\r\t""#, "\"\\/\u{8}\u{c}\n\r\t"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse_json(input.as_bytes())?, JsonValue::string(expected));
+        }
+
+        // offset of the offending `\`
+        let cases = [
+            (r#""\x""#, 1),
+            (r#"["ok", "a\q"]"#, 9),
+            (r#""\'""#, 1),
+            (r#""\u00G1""#, 1),
+            (r#""\u12""#, 1),
+            (r#""\ud83d""#, 1),
+            (r#""\ud83dx""#, 1),
+            (r#""\ud83d\u0041""#, 1),
+            (r#""ab\ude00""#, 3),
+        ];
+        for (input, offset) in cases {
+            let err = parse_json(input.as_bytes()).unwrap_err();
+            assert_eq!(
+                err.downcast_ref::<ParseError>(),
+                Some(&ParseError::InvalidEscape { offset }),
+                "{}",
+                input
+            );
+        }
+        assert_eq!(
+            parse_json(br#""\x""#).unwrap_err().to_string(),
+            "invalid escape at byte 1"
+        );
+
+        // relaxed mode keeps unknown escapes, but `\u` still has to be well formed
+        let relaxed = ParseOptions {
+            relaxed: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            parse_json_with_opts(br#""\x\u0041""#, &relaxed)?,
+            JsonValue::string("\\xA")
+        );
+        assert!(parse_json_with_opts(br#""\ud83d""#, &relaxed).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_array() -> PResult<(), ContextError> {
+        let input = r#"[1, 2, 3]"#;
+        let result: Vec<JsonValue> = parse_array::<_, _, JsonValue>(
+            &mut Located::new(input),
+            &ParseOptions::default(),
+            0,
+            &ParseState::default(),
+        )?;
+        assert_eq!(
+            result,
+            vec![
+                JsonValue::Number(Num::Int(1)),
+                JsonValue::Number(Num::Int(2)),
+                JsonValue::Number(Num::Int(3))
+            ]
+        );
+
+        let input = r#"["a", null, 1]"#;
+        let result: Vec<JsonValue> = parse_array::<_, _, JsonValue>(
+            &mut Located::new(input),
+            &ParseOptions::default(),
+            0,
+            &ParseState::default(),
+        )?;
+        assert_eq!(
+            result,
+            vec![
+                JsonValue::String("a".to_string()),
+                JsonValue::Null,
+                JsonValue::Number(Num::Int(1))
+            ]
+        );
+
+        let input = r#"[123.45, 122.3]"#;
+        let result: Vec<JsonValue> = parse_array::<_, _, JsonValue>(
+            &mut Located::new(input),
+            &ParseOptions::default(),
+            0,
+            &ParseState::default(),
+        )?;
+        println!("{:?}", result);
+        assert_eq!(
+            result,
+            vec![
+                JsonValue::Number(Num::Float(123.45)),
+                JsonValue::Number(Num::Float(122.3))
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
     fn test_parse_object() -> PResult<(), ContextError> {
         let input = r#"{"name": "John Doe", "age": 30}"#;
-        let result = parse_object(&mut (&*input))?;
-        let mut expected = HashMap::new();
+        let result: BTreeMap<_, JsonValue> = parse_object::<_, _, JsonValue>(
+            &mut Located::new(input),
+            &ParseOptions::default(),
+            0,
+            &ParseState::default(),
+        )?;
+        let mut expected = BTreeMap::new();
+        expected.insert(
+            "name".to_string(),
+            JsonValue::String("John Doe".to_string()),
+        );
+        expected.insert("age".to_string(), JsonValue::Number(Num::Int(30)));
+        assert_eq!(result, expected);
+
+        for input in ["{}", "{ }"] {
+            let result: BTreeMap<_, JsonValue> = parse_object::<_, _, JsonValue>(
+                &mut Located::new(input),
+                &ParseOptions::default(),
+                0,
+                &ParseState::default(),
+            )?;
+            assert!(result.is_empty());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_entries_and_elements() -> Result<()> {
+        let v = parse_json(SAMPLE.as_bytes())?;
+        let mut keys: Vec<_> = v.entries().map(|(k, _)| k).collect();
+        keys.sort();
+        assert_eq!(keys, ["address", "age", "is_student", "marks", "name"]);
+
+        let marks = v.entries().find(|(k, _)| *k == "marks").unwrap().1;
+        let marks: Vec<_> = marks.elements().collect();
+        assert_eq!(
+            marks,
+            [
+                &JsonValue::Number(Num::Int(90)),
+                &JsonValue::Number(Num::Int(-80)),
+                &JsonValue::Number(Num::Float(85.1))
+            ]
+        );
+
+        assert_eq!(v.elements().count(), 0);
+        assert_eq!(JsonValue::Null.entries().count(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_json_spanned() -> Result<()> {
+        let v = parse_json_spanned(SAMPLE.as_bytes(), &ParseOptions::default())?;
+        assert_eq!(v.span, 0..SAMPLE.len());
+        let SpannedValue::Object(root) = &v.value else {
+            panic!("expected an object");
+        };
+        let SpannedValue::Object(address) = &root["address"].value else {
+            panic!("expected an object");
+        };
+        let city = &address["city"];
+        assert_eq!(city.value, SpannedValue::String("New York".to_string()));
+        let start = SAMPLE.find(r#""New York""#).unwrap();
+        assert_eq!(city.span, start..start + 10);
+        assert_eq!(&SAMPLE[city.span.clone()], r#""New York""#);
+
+        let SpannedValue::Array(marks) = &root["marks"].value else {
+            panic!("expected an array");
+        };
+        assert_eq!(&SAMPLE[root["marks"].span.clone()], "[90, -80, 85.1]");
+        assert_eq!(&SAMPLE[marks[2].span.clone()], "85.1");
+        let v = parse_json_spanned(b"[[1] , 2 ]  ", &ParseOptions::default())?;
+        assert_eq!(v.span, 0..10);
+        let SpannedValue::Array(values) = &v.value else {
+            panic!("expected an array");
+        };
+        assert_eq!(values[0].span, 1..4);
+
+        let relaxed = ParseOptions {
+            relaxed: true,
+            ..Default::default()
+        };
+        let v = parse_json_spanned(b"[TRUE, 'a']", &relaxed)?;
+        let SpannedValue::Array(values) = &v.value else {
+            panic!("expected an array");
+        };
+        assert_eq!(values[1].span, 7..10);
+        assert!(parse_json_spanned(b"[TRUE]", &ParseOptions::default()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_json_with_stats() -> Result<()> {
+        let (v, stats) = parse_json_with_stats(SAMPLE.as_bytes())?;
+        assert_eq!(v, parse_json(SAMPLE.as_bytes())?);
+        assert_eq!(
+            stats,
+            ParseStats {
+                objects: 2,
+                arrays: 1,
+                strings: 2,
+                numbers: 5,
+                booleans: 1,
+                nulls: 0,
+                max_depth: 2,
+            }
+        );
+
+        let (_, stats) = parse_json_with_stats(b"null")?;
+        assert_eq!(stats.nulls, 1);
+        assert_eq!(stats.max_depth, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_flatten() -> Result<()> {
+        let v = parse_json(SAMPLE.as_bytes())?;
+        let pairs = flatten(&v);
+        assert_eq!(pairs.len(), 8);
+        assert_eq!(
+            pairs[0],
+            (
+                "address.city".to_string(),
+                JsonValue::String("New York".to_string())
+            )
+        );
+        assert!(pairs.contains(&("marks[2]".to_string(), JsonValue::Number(Num::Float(85.1)))));
+        assert!(pairs.contains(&("age".to_string(), JsonValue::Number(Num::Int(30)))));
+
+        assert_eq!(
+            flatten(&JsonValue::Bool(true)),
+            vec![(String::new(), JsonValue::Bool(true))]
+        );
+
+        let v = parse_json(br#"{"a": {}, "b": [{}]}"#)?;
+        assert_eq!(
+            flatten(&v),
+            vec![
+                ("a".to_string(), JsonValue::Object(BTreeMap::new())),
+                ("b[0]".to_string(), JsonValue::Object(BTreeMap::new())),
+            ]
+        );
+
+        let v = parse_json(br#"{"a.b": 1, "a": {"b": 2}, "c[0]\\": 3}"#)?;
+        let keys: Vec<_> = flatten(&v).into_iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, [r"a.b", r"a\.b", r"c\[0\]\\"]);
+        assert_eq!(unflatten(flatten(&v))?, v);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unflatten() -> Result<()> {
+        let v = parse_json(SAMPLE.as_bytes())?;
+        assert_eq!(unflatten(flatten(&v))?, v);
+
+        let pairs = vec![
+            ("a.b[1]".to_string(), JsonValue::Number(Num::Int(2))),
+            ("a.b[0]".to_string(), JsonValue::Number(Num::Int(1))),
+            ("a.c".to_string(), JsonValue::Null),
+        ];
+        let mut a = BTreeMap::new();
+        a.insert(
+            "b".to_string(),
+            JsonValue::Array(vec![
+                JsonValue::Number(Num::Int(1)),
+                JsonValue::Number(Num::Int(2)),
+            ]),
+        );
+        a.insert("c".to_string(), JsonValue::Null);
+        let mut expected = BTreeMap::new();
+        expected.insert("a".to_string(), JsonValue::Object(a));
+        assert_eq!(unflatten(pairs)?, JsonValue::Object(expected));
+
+        assert_eq!(
+            unflatten(vec![(String::new(), JsonValue::Bool(true))])?,
+            JsonValue::Bool(true)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_unflatten_conflict() {
+        let pairs = vec![
+            ("a".to_string(), JsonValue::Number(Num::Int(1))),
+            ("a.b".to_string(), JsonValue::Number(Num::Int(2))),
+        ];
+        let err = unflatten(pairs).unwrap_err();
+        assert!(err.to_string().contains("conflicting path \"a.b\""));
+
+        let pairs = vec![
+            ("a.b".to_string(), JsonValue::Number(Num::Int(2))),
+            ("a[0]".to_string(), JsonValue::Number(Num::Int(1))),
+        ];
+        assert!(unflatten(pairs).is_err());
+
+        let pairs = vec![("a[1]".to_string(), JsonValue::Null)];
+        assert!(unflatten(pairs).is_err());
+    }
+
+    #[test]
+    fn test_tokenize() -> Result<()> {
+        let tokens =
+            tokenize(br#"{"a":[1]}"#, &ParseOptions::default()).collect::<Result<Vec<_>>>()?;
+        let expected = [
+            (JsonToken::BeginObject, 0..1),
+            (JsonToken::String("a".to_string()), 1..4),
+            (JsonToken::Colon, 4..5),
+            (JsonToken::BeginArray, 5..6),
+            (JsonToken::Number(Num::Int(1)), 6..7),
+            (JsonToken::EndArray, 7..8),
+            (JsonToken::EndObject, 8..9),
+        ]
+        .map(|(value, span)| Spanned { value, span });
+        assert_eq!(tokens, expected);
+
+        let tokens = tokenize(b" [true , null,-2.5 ] ", &ParseOptions::default())
+            .collect::<Result<Vec<_>>>()?;
+        let expected = [
+            (JsonToken::BeginArray, 1..2),
+            (JsonToken::Bool(true), 2..6),
+            (JsonToken::Comma, 7..8),
+            (JsonToken::Null, 9..13),
+            (JsonToken::Comma, 13..14),
+            (JsonToken::Number(Num::Float(-2.5)), 14..18),
+            (JsonToken::EndArray, 19..20),
+        ]
+        .map(|(value, span)| Spanned { value, span });
+        assert_eq!(tokens, expected);
+        assert_eq!(tokenize(b" \n", &ParseOptions::default()).count(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_tokenize_error() {
+        let mut it = tokenize(b"[1, x]", &ParseOptions::default());
+        assert_eq!(it.next().unwrap().unwrap().value, JsonToken::BeginArray);
+        assert_eq!(
+            it.next().unwrap().unwrap().value,
+            JsonToken::Number(Num::Int(1))
+        );
+        assert_eq!(it.next().unwrap().unwrap().value, JsonToken::Comma);
+        let err = it.next().unwrap().unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ParseError>(),
+            Some(&ParseError::UnexpectedChar { offset: 4 })
+        );
+        assert!(it.next().is_none());
+
+        let err = tokenize(br#"["ab"#, &ParseOptions::default())
+            .collect::<Result<Vec<_>>>()
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ParseError>(),
+            Some(ParseError::UnterminatedString { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_json_with_warnings() -> Result<()> {
+        let input = br#"{"a": 1, "b": {"c": [{"d": 1, "d": 2}]}, "a": 3}"#;
+        let (v, warnings) = parse_json_with_warnings(input)?;
+        assert_eq!(v, parse_json(br#"{"a": 3, "b": {"c": [{"d": 2}]}}"#)?);
+        assert_eq!(
+            warnings,
+            [
+                Warning::DuplicateKey {
+                    key: "d".to_string(),
+                    path: "/b/c/0/d".to_string(),
+                    offset: 30,
+                },
+                Warning::DuplicateKey {
+                    key: "a".to_string(),
+                    path: "/a".to_string(),
+                    offset: 41,
+                },
+            ]
+        );
+        for w in &warnings {
+            let Warning::DuplicateKey { key, path, offset } = w;
+            assert_eq!(
+                &input[*offset..*offset + key.len() + 2],
+                format!("\"{}\"", key).as_bytes()
+            );
+            assert!(v.pointer(path).is_some());
+        }
+
+        // a key equal to a string value, or the same key in another object, isn't a duplicate
+        let (_, warnings) =
+            parse_json_with_warnings(br#"{"a": "a", "b": [{"a": 1}, {"a/~": 2, "a/~": 3}]}"#)?;
+        assert_eq!(
+            warnings,
+            [Warning::DuplicateKey {
+                key: "a/~".to_string(),
+                path: "/b/1/a~1~0".to_string(),
+                offset: 38,
+            }]
+        );
+        assert!(parse_json_with_warnings(br#"{"a": 1, "a": }"#).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_json_into() -> Result<()> {
+        // tallies the nodes instead of building a tree
+        #[derive(Debug, Default)]
+        struct Tally {
+            stats: ParseStats,
+            last_span: Range<usize>,
+        }
+        impl Tally {
+            fn leaf(span: Range<usize>, f: impl FnOnce(&mut ParseStats)) -> Self {
+                let mut stats = ParseStats::default();
+                f(&mut stats);
+                Tally {
+                    stats,
+                    last_span: span,
+                }
+            }
+
+            fn add(&mut self, other: Tally) {
+                let (a, b) = (&mut self.stats, other.stats);
+                a.objects += b.objects;
+                a.arrays += b.arrays;
+                a.strings += b.strings;
+                a.numbers += b.numbers;
+                a.booleans += b.booleans;
+                a.nulls += b.nulls;
+                a.max_depth = a.max_depth.max(b.max_depth + 1);
+            }
+        }
+        impl ValueBuilder for Tally {
+            type Elements = Tally;
+            type Members = Tally;
+
+            fn build_null(span: Range<usize>) -> Self {
+                Tally::leaf(span, |s| s.nulls += 1)
+            }
+            fn build_bool(_b: bool, span: Range<usize>) -> Self {
+                Tally::leaf(span, |s| s.booleans += 1)
+            }
+            fn build_number(_n: Num, span: Range<usize>) -> Self {
+                Tally::leaf(span, |s| s.numbers += 1)
+            }
+            fn build_string(_s: String, span: Range<usize>) -> Self {
+                Tally::leaf(span, |s| s.strings += 1)
+            }
+            fn push_array_elem(array: &mut Tally, value: Self) {
+                array.add(value);
+            }
+            fn insert_object_key(object: &mut Tally, _key: String, value: Self) {
+                object.add(value);
+            }
+            fn build_array(mut array: Tally, span: Range<usize>) -> Self {
+                array.stats.arrays += 1;
+                array.stats.max_depth = array.stats.max_depth.max(1);
+                array.last_span = span;
+                array
+            }
+            fn build_object(mut object: Tally, span: Range<usize>) -> Self {
+                object.stats.objects += 1;
+                object.stats.max_depth = object.stats.max_depth.max(1);
+                object.last_span = span;
+                object
+            }
+        }
+
+        let tally: Tally = parse_json_into(SAMPLE.as_bytes(), &ParseOptions::default())?;
+        assert_eq!(tally.stats, parse_json_with_stats(SAMPLE.as_bytes())?.1);
+        assert_eq!(tally.last_span, 0..SAMPLE.len());
+        let tally: Tally = parse_json_into(b" [[], {}, [null]] ", &ParseOptions::default())?;
+        assert_eq!(
+            tally.stats,
+            ParseStats {
+                objects: 1,
+                arrays: 3,
+                nulls: 1,
+                max_depth: 2,
+                ..Default::default()
+            }
+        );
+        assert_eq!(tally.last_span, 1..17);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_json_partial() {
+        let doc = br#" {"name": "Jo\"hn", "marks": [90, -85.1], "ok": true, "x": null} "#;
+        let expected = parse_json(doc).unwrap();
+        // cut anywhere before the closing brace, the value needs more input
+        for split in 0..doc.len() - 1 {
+            let result = parse_json_partial(&mut Partial::new(&doc[..split]));
+            assert!(matches!(result, Err(ErrMode::Incomplete(_))), "{}", split);
+        }
+        let mut input = Partial::new(&doc[..]);
+        assert_eq!(parse_json_partial(&mut input).unwrap(), expected);
+        assert_eq!(*input, b" ");
+
+        // a value arriving in two chunks
+        let mut buf = br#"{"a": [1, 2"#.to_vec();
+        let result = parse_json_partial(&mut Partial::new(&buf[..]));
+        assert!(matches!(result, Err(ErrMode::Incomplete(_))));
+        buf.extend_from_slice(br#", 3]}{"b"#);
+        let mut input = Partial::new(&buf[..]);
+        assert_eq!(
+            parse_json_partial(&mut input).unwrap(),
+            parse_json(br#"{"a": [1, 2, 3]}"#).unwrap()
+        );
+        assert_eq!(*input, br#"{"b"#);
+
+        // more digits could follow until the input is marked complete
+        let mut input = Partial::new(&b"42"[..]);
+        assert!(matches!(
+            parse_json_partial(&mut input),
+            Err(ErrMode::Incomplete(_))
+        ));
+        let _ = input.complete();
+        assert_eq!(parse_json_partial(&mut input).unwrap(), JsonValue::int(42));
+
+        let result = parse_json_partial(&mut Partial::new(&b"[1, }"[..]));
+        assert!(matches!(result, Err(ErrMode::Cut(_))));
+    }
+
+    #[test]
+    fn test_parse_array_stream() -> Result<()> {
+        let values = parse_array_stream(b" [1, 2 ,3] ", &ParseOptions::default())
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(
+            values,
+            vec![
+                JsonValue::Number(Num::Int(1)),
+                JsonValue::Number(Num::Int(2)),
+                JsonValue::Number(Num::Int(3))
+            ]
+        );
+        assert_eq!(
+            parse_array_stream(b"[]", &ParseOptions::default()).count(),
+            0
+        );
+
+        let mut it = parse_array_stream(b"[1, [2]", &ParseOptions::default());
+        assert_eq!(it.next().unwrap()?, JsonValue::Number(Num::Int(1)));
+        assert_eq!(
+            it.next().unwrap()?,
+            JsonValue::Array(vec![JsonValue::Number(Num::Int(2))])
+        );
+        assert!(it.next().unwrap().is_err());
+        assert!(it.next().is_none());
+
+        // elements sit at depth 1
+        let opts = ParseOptions {
+            max_depth: 1,
+            ..Default::default()
+        };
+        let mut it = parse_array_stream(b"[1, [2]]", &opts);
+        assert_eq!(it.next().unwrap()?, JsonValue::Number(Num::Int(1)));
+        assert!(it.next().unwrap().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_json_seq() -> Result<()> {
+        let opts = ParseOptions::default();
+        let values = parse_json_seq(b"{\"a\":1} [1,2]\n\ttrue\n", &opts)?;
+        let mut a = BTreeMap::new();
+        a.insert("a".to_string(), JsonValue::Number(Num::Int(1)));
+        assert_eq!(
+            values,
+            vec![
+                JsonValue::Object(a),
+                JsonValue::Array(vec![
+                    JsonValue::Number(Num::Int(1)),
+                    JsonValue::Number(Num::Int(2))
+                ]),
+                JsonValue::Bool(true),
+            ]
+        );
+        // a value may span lines
+        assert_eq!(parse_json_seq(b"[1,\n2]\n3", &opts)?.len(), 2);
+        assert_eq!(parse_json_seq(b"  ", &opts)?, vec![]);
+
+        let err = parse_json_seq(b"1 [2, ", &opts).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ParseError>(),
+            Some(&ParseError::UnexpectedEof { offset: 6 })
+        );
+        let err = parse_json_seq(b"[1]2", &opts).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ParseError>(),
+            Some(&ParseError::UnexpectedChar { offset: 3 })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_json_owned() -> Result<()> {
+        fn assert_static<T: 'static>(_: &T) {}
+
+        let v = {
+            let buf = SAMPLE.as_bytes().to_vec();
+            parse_json_owned(buf)?
+        };
+        assert_static(&v);
+        assert_eq!(v, parse_json(SAMPLE.as_bytes())?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_hashable_value() -> Result<()> {
+        use std::collections::HashSet;
+
+        let mut set = HashSet::new();
+        set.insert(HashableValue(parse_json(SAMPLE.as_bytes())?));
+        set.insert(HashableValue(parse_json(SAMPLE.as_bytes())?));
+        set.insert(HashableValue(parse_json(b"[1, 2]")?));
+        set.insert(HashableValue(parse_json(b"[1,2]")?));
+        set.insert(HashableValue(parse_json(b"[1.0, 2]")?));
+        set.insert(HashableValue(JsonValue::Number(Num::Float(f64::NAN))));
+        set.insert(HashableValue(JsonValue::Number(Num::Float(f64::NAN))));
+        set.insert(HashableValue(JsonValue::Number(Num::Float(0.0))));
+        set.insert(HashableValue(JsonValue::Number(Num::Float(-0.0))));
+        assert_eq!(set.len(), 5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bare_keys() -> Result<()> {
+        let opts = ParseOptions {
+            relaxed: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            parse_json_with_opts(b"{name: 1}", &opts)?,
+            parse_json(br#"{"name": 1}"#)?
+        );
+        assert_eq!(
+            parse_json_with_opts(b"{ $a_1 : 2, _b: {c3: [true]}, 'd': null }", &opts)?,
+            parse_json(br#"{"$a_1": 2, "_b": {"c3": [true]}, "d": null}"#)?
+        );
+        for input in ["{1a: 1}", "{a-b: 1}", "{a b: 1}", "{: 1}"] {
+            assert!(
+                parse_json_with_opts(input.as_bytes(), &opts).is_err(),
+                "{}",
+                input
+            );
+        }
+        assert!(parse_json(b"{name: 1}").is_err());
+        // only keys, a bare word is still not a value
+        assert!(parse_json_with_opts(b"{a: b}", &opts).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_comments() -> Result<()> {
+        let opts = ParseOptions {
+            relaxed: true,
+            ..Default::default()
+        };
+        let expected = parse_json(br#"{"a": [1, 2], "b": true}"#)?;
+        let inputs: [&[u8]; 4] = [
+            b"{\"a\" /* before colon */ : [1, 2], \"b\": true}",
+            b"{\"a\": [1 // before comma\n, 2], \"b\": true}",
+            b"{\"a\": [1, 2 /* before ] */], \"b\" // key\n: true}",
+            b"// leading\n{\"a\": [1, /**/2], \"b\": true} /* trailing */",
+        ];
+        for input in inputs {
+            assert_eq!(parse_json_with_opts(input, &opts)?, expected);
+            assert!(parse_json(input).is_err());
+        }
+        assert!(parse_json_with_opts(b"[1 /* unterminated ]", &opts).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_error_kinds() {
+        let cases: [(&[u8], ParseError); 9] = [
+            (b"[1, }", ParseError::UnexpectedChar { offset: 4 }),
+            (b"[1, 2", ParseError::UnexpectedEof { offset: 5 }),
+            (
+                br#"{"a": "abc"#,
+                ParseError::UnterminatedString { offset: 6 },
+            ),
+            (b"[-x]", ParseError::InvalidNumber { offset: 1 }),
+            (b"[1.]", ParseError::InvalidNumber { offset: 1 }),
+            (
+                b"99999999999999999999",
+                ParseError::InvalidNumber { offset: 0 },
+            ),
+            (b"[[[]]]", ParseError::DepthExceeded { offset: 3 }),
+            (b"null null", ParseError::TrailingData { offset: 5 }),
+            (b"\"\xff\"", ParseError::InvalidUtf8 { offset: 1 }),
+        ];
+        let opts = ParseOptions {
+            max_depth: 2,
+            ..Default::default()
+        };
+        for (input, expected) in cases {
+            let err = parse_json_with_opts(input, &opts).unwrap_err();
+            assert_eq!(err.downcast_ref::<ParseError>(), Some(&expected));
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_parse_json_async() -> Result<()> {
+        let v = parse_json_async(std::io::Cursor::new(SAMPLE.as_bytes())).await?;
+        assert_eq!(v, parse_json(SAMPLE.as_bytes())?);
+
+        let err = parse_json_async(std::io::Cursor::new(b"[1, }"))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ParseError>(),
+            Some(&ParseError::UnexpectedChar { offset: 4 })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_display() -> Result<()> {
+        let v = parse_json(SAMPLE.as_bytes())?;
+        assert_eq!(
+            v.to_string(),
+            r#"{"address":{"city":"New York","zip":10001},"age":30,"is_student":false,"marks":[90,-80,85.1],"name":"John Doe"}"#
+        );
+        assert_eq!(parse_json(v.to_string().as_bytes())?, v);
+
+        let cases = [
+            (JsonValue::float(1.0), "1.0"),
+            (JsonValue::float(-0.0), "-0.0"),
+            (JsonValue::float(1e20), "100000000000000000000.0"),
+            (JsonValue::float(0.1), "0.1"),
+            (JsonValue::float(f64::NAN), "null"),
+            (JsonValue::float(f64::INFINITY), "null"),
+            (JsonValue::Number(Num::Raw("1.50".to_string())), "1.50"),
+            (
+                JsonValue::string("a\"b\\c\n\t\u{1}é"),
+                r#""a\"b\\c\n\t\u0001é""#,
+            ),
+            (JsonValue::array(), "[]"),
+            (JsonValue::object(), "{}"),
+        ];
+        for (v, expected) in cases {
+            assert_eq!(v.to_string(), expected);
+            // reads back as the same value, except what JSON can't express
+            let back = parse_json(expected.as_bytes())?;
+            match v {
+                JsonValue::Number(Num::Float(n)) if !n.is_finite() => {
+                    assert_eq!(back, JsonValue::Null)
+                }
+                JsonValue::Number(Num::Raw(ref s)) => {
+                    assert_eq!(back, JsonValue::float(s.parse().unwrap()))
+                }
+                v => assert_eq!(back, v, "{}", expected),
+            }
+        }
+
+        // every character that has to be escaped
+        let all: String = (0..0x20u8)
+            .map(char::from)
+            .chain(['"', '\\', '/', '\u{7f}'])
+            .collect();
+        let v = JsonValue::Array(vec![
+            JsonValue::string(all.clone()),
+            JsonValue::string("\u{1}"),
+        ]);
+        for opts in [
+            WriteOptions::default(),
+            WriteOptions {
+                escape_solidus: true,
+            },
+        ] {
+            let s = v.display_with(&opts).to_string();
+            let back = parse_json(s.as_bytes())?;
+            assert_eq!(back, v, "{}", s);
+            // and again, nothing accumulates
+            assert_eq!(back.display_with(&opts).to_string(), s);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_display_float() -> Result<()> {
+        // what `String(n)` gives in JS, except that integral floats keep their `.0`
+        let cases = [
+            (0.1, "0.1"),
+            (1e21, "1e+21"),
+            (1e-7, "1e-7"),
+            (core::f64::consts::PI, "3.141592653589793"),
+            (-1.5e-7, "-1.5e-7"),
+            (0.000001, "0.000001"),
+            (123456.789, "123456.789"),
+            (1.7976931348623157e308, "1.7976931348623157e+308"),
+            (5e-324, "5e-324"),
+            (1.5e300, "1.5e+300"),
+            (-100.0, "-100.0"),
+        ];
+        for (n, expected) in cases {
+            let s = JsonValue::float(n).to_string();
+            assert_eq!(s, expected);
+            assert_eq!(parse_json(s.as_bytes())?, JsonValue::float(n), "{}", s);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_solidus() -> Result<()> {
+        let v = parse_json(br#""a\/b""#)?;
+        assert_eq!(v, JsonValue::string("a/b"));
+        assert_eq!(parse_json(br#""a/b""#)?, v);
+        assert_eq!(
+            parse_json(br#"{"\/": "<\/script>"}"#)?.get("/"),
+            Some(&JsonValue::string("</script>"))
+        );
+
+        let v = parse_json(br#"{"url": "http:\/\/example.com/"}"#)?;
+        assert_eq!(v.to_string(), r#"{"url":"http://example.com/"}"#);
+        let opts = WriteOptions {
+            escape_solidus: true,
+        };
+        let escaped = v.display_with(&opts).to_string();
+        assert_eq!(escaped, r#"{"url":"http:\/\/example.com\/"}"#);
+        assert_eq!(parse_json(escaped.as_bytes())?, v);
+
+        // next to a `\u` escape, printed with and without escaping the solidus
+        let v = parse_json(br#""a\/b\u0001""#)?;
+        assert_eq!(v, JsonValue::string("a/b\u{1}"));
+        assert_eq!(v.to_string(), r#""a/b\u0001""#);
+        assert_eq!(v.display_with(&opts).to_string(), r#""a\/b\u0001""#);
+        assert_eq!(parse_json(v.display_with(&opts).to_string().as_bytes())?, v);
+        Ok(())
+    }
+
+    #[test]
+    fn test_minify() -> Result<()> {
+        let minified = minify(SAMPLE)?;
+        // unlike `to_string` the keys stay in document order
+        assert_eq!(
+            minified,
+            r#"{"name":"John Doe","age":30,"is_student":false,"marks":[90,-80,85.1],"address":{"city":"New York","zip":10001}}"#
+        );
+        assert_eq!(
+            parse_json(minified.as_bytes())?,
+            parse_json(SAMPLE.as_bytes())?
+        );
+
+        // string contents, escapes and number spellings are kept exactly
+        let s = "{ \"a  b\" : [ \" x \\\" y \\\\\", 1.50 , 1E3 ] ,\n \"c\":\ttrue }";
+        assert_eq!(minify(s)?, r#"{"a  b":[" x \" y \\",1.50,1E3],"c":true}"#);
+        assert_eq!(minify("  \"\\u00e9 \"  ")?, r#""\u00e9 ""#);
+
+        let cases = [
+            ("[1, \"ab]", ParseError::UnterminatedString { offset: 4 }),
+            ("[1, {]", ParseError::UnexpectedChar { offset: 5 }),
+            ("[1, [2]", ParseError::UnexpectedEof { offset: 7 }),
+            ("[1 2]", ParseError::UnexpectedChar { offset: 3 }),
+            (" \n ", ParseError::EmptyInput { offset: 3 }),
+        ];
+        for (input, expected) in cases {
+            let err = minify(input).unwrap_err();
+            assert_eq!(
+                err.downcast_ref::<ParseError>(),
+                Some(&expected),
+                "{}",
+                input
+            );
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_to_writer() -> Result<()> {
+        let v = parse_json(SAMPLE.as_bytes())?;
+        let mut out = Vec::new();
+        to_writer(&v, &mut out)?;
+        assert_eq!(parse_json(&out)?, v);
+        assert_eq!(String::from_utf8(out)?, v.to_string());
+        let v = JsonValue::string("a\u{1}\"b\u{1f}");
+        let mut out = Vec::new();
+        to_writer(&v, &mut out)?;
+        assert_eq!(out, br#""a\u0001\"b\u001f""#);
+        assert_eq!(parse_json(&out)?, v);
+        let mut out = Vec::new();
+        let opts = WriteOptions {
+            escape_solidus: true,
+        };
+        to_writer_with_opts(&JsonValue::string("a/b"), &mut out, &opts)?;
+        assert_eq!(out, br#""a\/b""#);
+        assert_eq!(parse_json(&out)?, JsonValue::string("a/b"));
+
+        // a sink that fails after a few bytes
+        struct Full(usize);
+        impl std::io::Write for Full {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                if self.0 < buf.len() {
+                    return Err(std::io::ErrorKind::WriteZero.into());
+                }
+                self.0 -= buf.len();
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let err = to_writer(&v, Full(10)).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WriteZero);
+        Ok(())
+    }
+
+    #[test]
+    fn test_assert_json_eq() {
+        assert_json_eq(r#"{"a":1,"b":2}"#, r#"{ "b":2, "a":1 }"#);
+        assert_json_eq("[1, [true, null]]", "[1,[true,null]]\n");
+    }
+
+    #[test]
+    #[should_panic(expected = r#"{"a":1} != {"a":2}"#)]
+    fn test_assert_json_eq_unequal() {
+        assert_json_eq(r#"{"a":1}"#, r#"{"a":2}"#);
+    }
+
+    #[test]
+    #[should_panic(expected = "unexpected character")]
+    fn test_assert_json_eq_invalid() {
+        assert_json_eq("[1,]", "[1]");
+    }
+
+    #[test]
+    fn test_constructors() -> Result<()> {
+        assert_eq!(JsonValue::default(), JsonValue::Null);
+        assert_eq!(JsonValue::object(), JsonValue::Object(BTreeMap::new()));
+        assert_eq!(JsonValue::array(), JsonValue::Array(vec![]));
+        assert_eq!(JsonValue::string("a"), JsonValue::String("a".to_string()));
+        assert_eq!(
+            JsonValue::string(String::from("b")),
+            JsonValue::String("b".to_string())
+        );
+        assert_eq!(JsonValue::int(-3), JsonValue::Number(Num::Int(-3)));
+        assert_eq!(JsonValue::float(2.5), JsonValue::Number(Num::Float(2.5)));
+
+        let mut v = JsonValue::object();
+        v.insert("name", JsonValue::string("John Doe"))?;
+        v.insert("age", JsonValue::int(30))?;
+        v.insert("marks", JsonValue::Array(vec![JsonValue::float(85.1)]))?;
+        v.insert("spouse", JsonValue::default())?;
+        assert_eq!(
+            v,
+            parse_json(br#"{"name": "John Doe", "age": 30, "marks": [85.1], "spouse": null}"#)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_object_mutation() -> Result<()> {
+        let mut v = parse_json(SAMPLE.as_bytes())?;
+        let email = JsonValue::String("john@example.com".to_string());
+        assert_eq!(v.insert("email", email.clone())?, None);
+        assert_eq!(v.get("email"), Some(&email));
+        assert_eq!(
+            v.insert("age", JsonValue::Number(Num::Int(31)))?,
+            Some(JsonValue::Number(Num::Int(30)))
+        );
+        assert_eq!(v.get_as::<i64>("age")?, 31);
+
+        assert_eq!(v.remove("is_student"), Some(JsonValue::Bool(false)));
+        assert_eq!(v.remove("is_student"), None);
+        assert_eq!(v.get("is_student"), None);
+
+        if let Some(JsonValue::Array(marks)) =
+            v.entry("marks").map(|e| e.or_insert(JsonValue::Null))
+        {
+            marks.push(JsonValue::Number(Num::Int(70)));
+        }
+        assert_eq!(v.get_as::<Vec<f64>>("marks")?, [90.0, -80.0, 85.1, 70.0]);
+        v.entry("tags")
+            .unwrap()
+            .or_insert_with(|| JsonValue::Array(vec![]));
+        assert_eq!(v.get("tags"), Some(&JsonValue::Array(vec![])));
+
+        let mut arr = JsonValue::Array(vec![]);
+        assert!(arr.insert("a", JsonValue::Null).is_err());
+        assert_eq!(arr.remove("a"), None);
+        assert!(arr.entry("a").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_into_accessors() -> Result<()> {
+        let v = parse_json(SAMPLE.as_bytes())?;
+        let mut map = v.into_object().map_err(|_| anyhow!("expected an object"))?;
+        let marks = map
+            .remove("marks")
+            .ok_or_else(|| anyhow!("missing marks"))?
+            .into_array()
+            .map_err(|_| anyhow!("expected an array"))?;
+        assert_eq!(
+            marks,
+            [
+                JsonValue::int(90),
+                JsonValue::int(-80),
+                JsonValue::float(85.1)
+            ]
+        );
+        assert_eq!(marks[2].clone().into_f64(), Ok(85.1));
+        assert_eq!(marks[0].clone().into_f64(), Ok(90.0));
+
+        // the wrong kind gives the value itself back
+        let name = map.remove("name").ok_or_else(|| anyhow!("missing name"))?;
+        assert_eq!(name.clone().into_i64(), Err(name.clone()));
+        assert_eq!(name.clone().into_array(), Err(name.clone()));
+        assert_eq!(name.into_string(), Ok("John Doe".to_string()));
+        assert_eq!(JsonValue::float(2.5).into_i64(), Err(JsonValue::float(2.5)));
+        assert_eq!(JsonValue::Bool(true).into_bool(), Ok(true));
+
+        let mut v = parse_json(SAMPLE.as_bytes())?;
+        let mut address = v.entry("address").and_then(|e| match e {
+            btree_map::Entry::Occupied(mut e) => Some(e.get_mut().take()),
+            btree_map::Entry::Vacant(_) => None,
+        });
+        assert_eq!(
+            address.as_mut().and_then(|a| a.remove("zip")),
+            Some(JsonValue::int(10001))
+        );
+        assert_eq!(v.get("address"), Some(&JsonValue::Null));
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_values() -> Result<()> {
+        let mut v = parse_json(SAMPLE.as_bytes())?;
+        v.map_values(|v| {
+            if let JsonValue::String(s) = v {
+                *s = s.to_uppercase();
+            }
+        });
+        let expected = SAMPLE
+            .replace("John Doe", "JOHN DOE")
+            .replace("New York", "NEW YORK");
+        assert_eq!(v, parse_json(expected.as_bytes())?);
+
+        // pre-order, and a replaced node's new children are visited
+        let mut v = parse_json(br#"{"b": [1, {"c": 2}], "a": 3}"#)?;
+        let mut seen = vec![];
+        v.map_values(|v| {
+            if let JsonValue::Number(Num::Int(n)) = v {
+                seen.push(*n);
+                if *n == 3 {
+                    *v = JsonValue::Array(vec![JsonValue::int(4)]);
+                }
+            }
+        });
+        assert_eq!(seen, [3, 4, 1, 2]);
+        assert_eq!(v, parse_json(br#"{"a": [4], "b": [1, {"c": 2}]}"#)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pointer() -> Result<()> {
+        let v = parse_json(SAMPLE.as_bytes())?;
+        assert_eq!(
+            v.pointer("/address/city"),
+            Some(&JsonValue::String("New York".to_string()))
+        );
+        assert_eq!(
+            v.pointer("/marks/2"),
+            Some(&JsonValue::Number(Num::Float(85.1)))
+        );
+        assert_eq!(v.pointer(""), Some(&v));
+        for ptr in [
+            "address",
+            "/marks/3",
+            "/marks/-",
+            "/marks/01",
+            "/marks/+1",
+            "/age/0",
+        ] {
+            assert_eq!(v.pointer(ptr), None, "{}", ptr);
+        }
+
+        let v = parse_json(br#"{"a/b": {"m~n": 1}, "": 2}"#)?;
+        assert_eq!(
+            v.pointer("/a~1b/m~0n"),
+            Some(&JsonValue::Number(Num::Int(1)))
+        );
+        assert_eq!(v.pointer("/a/b"), None);
+        assert_eq!(v.pointer("/"), Some(&JsonValue::Number(Num::Int(2))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_json() -> Result<()> {
+        struct Person {
+            name: String,
+            age: i64,
+            marks: Vec<f64>,
+        }
+
+        impl FromJson for Person {
+            fn from_json(v: &JsonValue) -> Result<Self> {
+                Ok(Person {
+                    name: v.get_as("name")?,
+                    age: v.get_as("age")?,
+                    marks: v.get_as("marks")?,
+                })
+            }
+        }
+
+        let v = parse_json(SAMPLE.as_bytes())?;
+        let person = Person::from_json(&v)?;
+        assert_eq!(person.name, "John Doe");
+        assert_eq!(person.age, 30);
+        assert_eq!(person.marks.len(), 3);
+        assert!(v.get_as::<bool>("is_student").is_ok());
+
+        let err = v.get_as::<i64>("name").unwrap_err();
+        assert_eq!(err.to_string(), "field `name`: expected an integer");
+        let err = v.get_as::<i64>("height").unwrap_err();
+        assert_eq!(err.to_string(), "missing field `height`");
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_json_bom() -> Result<()> {
+        let input = [b"\xEF\xBB\xBF".as_slice(), SAMPLE.as_bytes()].concat();
+        assert_eq!(parse_json(&input)?, parse_json(SAMPLE.as_bytes())?);
+        assert_eq!(
+            parse_json(b"\xEF\xBB\xBF \n 1")?,
+            JsonValue::Number(Num::Int(1))
+        );
+
+        let v = parse_json_spanned(b"\xEF\xBB\xBF[1]", &ParseOptions::default())?;
+        assert_eq!(v.span, 3..6);
+        let err = parse_json(b"\xEF\xBB\xBF[1,]").unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ParseError>(),
+            Some(&ParseError::UnexpectedChar { offset: 6 })
+        );
+        // only one, and only at the start
+        assert!(parse_json(b"\xEF\xBB\xBF\xEF\xBB\xBF1").is_err());
+        assert!(parse_json(b" \xEF\xBB\xBF1").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_json_element_limits() -> Result<()> {
+        let opts = ParseOptions {
+            max_elements: 3,
+            ..Default::default()
+        };
+        assert!(parse_json_with_opts(b"[1, 2, 3]", &opts).is_ok());
+        let err = parse_json_with_opts(b"[1, 2, 3, 4]", &opts).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ParseError>(),
+            Some(&ParseError::ElementLimitExceeded { offset: 10 })
+        );
+        assert_eq!(err.to_string(), "element limit exceeded at byte 10");
+        assert!(parse_json_with_opts(br#"{"a": 1, "b": 2, "c": 3, "d": 4}"#, &opts).is_err());
+
+        // 3 arrays and 6 numbers
+        let input = b"[[1, 2, 3], [4, 5, 6]]";
+        let opts = ParseOptions {
+            max_nodes: 9,
+            ..Default::default()
+        };
+        assert!(parse_json_with_opts(input, &opts).is_ok());
+        let opts = ParseOptions {
+            max_nodes: 8,
+            ..Default::default()
+        };
+        assert!(parse_json_with_opts(input, &opts).is_err());
+
+        // an empty container holds no element and is a single node
+        let opts = ParseOptions {
+            max_elements: 0,
+            max_nodes: 1,
+            ..Default::default()
+        };
+        assert!(parse_json_with_opts(b"[]", &opts).is_ok());
+        assert!(parse_json_with_opts(b"[1]", &opts).is_err());
+        let opts = ParseOptions {
+            max_elements: 1,
+            ..Default::default()
+        };
+        assert!(parse_json_with_opts(b"[]", &opts).is_ok());
+        let opts = ParseOptions {
+            max_nodes: 0,
+            ..Default::default()
+        };
+        assert!(parse_json_with_opts(b"[]", &opts).is_err());
+        Ok(())
+    }
+}
+
+// run with `--no-default-features`. test builds always link std, so CI also builds the library
+// for a target without std to make sure the parser doesn't need it
+#[cfg(all(test, not(feature = "std")))]
+mod no_std_tests {
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn parse_value_without_std() -> PResult<(), ContextError> {
+        let input = br#"{"marks": [90, -80, 85.1], "ok": true}"#;
+        let v = parse_value(
+            &mut Located::new(&input[..]),
+            &ParseOptions::default(),
+            0,
+            &ParseState::default(),
+        )?;
+        let mut expected = BTreeMap::new();
         expected.insert(
-            "name".to_string(),
-            JsonValue::String("John Doe".to_string()),
+            "marks".to_string(),
+            JsonValue::Array(vec![
+                JsonValue::Number(Num::Int(90)),
+                JsonValue::Number(Num::Int(-80)),
+                JsonValue::Number(Num::Float(85.1)),
+            ]),
         );
-        expected.insert("age".to_string(), JsonValue::Number(Num::Int(30)));
-        assert_eq!(result, expected);
+        expected.insert("ok".to_string(), JsonValue::Bool(true));
+        assert_eq!(v, JsonValue::Object(expected));
         Ok(())
     }
 }
@@ -1,47 +1,67 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
-use anyhow::Result;
-use pest::{iterators::Pair, Parser};
+use anyhow::{anyhow, Result};
+use pest::{error::LineColLocation, iterators::Pair, Parser};
 use pest_derive::Parser;
 
+use crate::{
+    error::ParseError,
+    json::{JsonValue, Num},
+};
+
 #[derive(Parser)]
 #[grammar = "json.pest"]
 struct JsonParser;
 
-#[allow(unused)]
-#[derive(Debug, PartialEq)]
-enum JsonValue {
-    Null,
-    Bool(bool),
-    Number(f64),
-    String(String),
-    Array(Vec<JsonValue>),
-    Object(HashMap<String, JsonValue>),
+// the pest counterpart of `json::parse_json`, same input and output so the two backends can be
+// swapped and compared: any JSON value as the document, strings with their escapes decoded.
+// only the error values differ
+pub fn parse(input: &[u8]) -> Result<JsonValue> {
+    let s = std::str::from_utf8(input)?;
+    // the same error as the winnow parser rather than pest's "expected object or array"
+    if s.trim_start_matches([' ', '\t', '\n', '\r']).is_empty() {
+        return Err(anyhow!(ParseError::EmptyInput { offset: s.len() }));
+    }
+    let mut pairs = JsonParser::parse(Rule::document, s).map_err(render_error)?;
+    match pairs.next() {
+        Some(pair) => parse_value(pair),
+        None => Err(anyhow!(ParseError::UnexpectedEof { offset: s.len() })),
+    }
 }
 
-fn main() -> Result<()> {
-    let s = r#"{
-        "name": "John Doe",
-        "age": 30,
-        "is_student": false,
-        "marks": [90, -80, 85.1],
-        "address": {
-            "city": "New York",
-            "zip": 10001
-        }
-    }"#;
-    let parsed = JsonParser::parse(Rule::json, s)?.next().unwrap();
-    let v = parse_value(parsed)?;
-    println!("{:#?}", v);
-    Ok(())
+// pest already draws the offending line with a caret under it, add where that is and use
+// names a user would recognise instead of the grammar's rule names
+fn render_error(e: pest::error::Error<Rule>) -> anyhow::Error {
+    let (line, col) = match e.line_col {
+        LineColLocation::Pos(pos) | LineColLocation::Span(pos, _) => pos,
+    };
+    let e = e.renamed_rules(|rule| match rule {
+        Rule::value => "a JSON value".to_string(),
+        Rule::pair => "a `\"key\": value` pair".to_string(),
+        Rule::object => "an object".to_string(),
+        Rule::array => "an array".to_string(),
+        Rule::chars | Rule::unescaped | Rule::escaped => "string characters".to_string(),
+        Rule::EOI => "end of input".to_string(),
+        rule => format!("{:?}", rule),
+    });
+    anyhow!("invalid JSON at line {}, column {}\n{}", line, col, e)
 }
 
+// the grammar only hands `parse_value` shapes it knows, but a caller can parse with any rule.
+// whatever doesn't fit is an error pointing at the pair's span rather than a panic
 fn parse_value(pair: Pair<Rule>) -> Result<JsonValue> {
     let ret = match pair.as_rule() {
         Rule::null => JsonValue::Null,
         Rule::bool => JsonValue::Bool(pair.as_str() == "true"),
-        Rule::number => JsonValue::Number(pair.as_str().parse()?),
-        Rule::chars => JsonValue::String(pair.as_str().to_string()),
+        // like the winnow parser, numbers without a fraction or exponent stay integers
+        Rule::number => match pair.as_str().parse() {
+            Ok(n) => JsonValue::Number(Num::Int(n)),
+            Err(_) => match pair.as_str().parse::<f64>() {
+                Ok(n) if n.is_finite() => JsonValue::Number(Num::Float(n)),
+                _ => return Err(span_error(&pair, "invalid number")),
+            },
+        },
+        Rule::chars => JsonValue::String(unescape(&pair)?),
         Rule::array => {
             let mut values = vec![];
             for inner_pair in pair.into_inner() {
@@ -50,39 +70,105 @@ fn parse_value(pair: Pair<Rule>) -> Result<JsonValue> {
             JsonValue::Array(values)
         }
         Rule::object => {
-            let mut map = HashMap::new();
-            let mut key = "";
+            let mut map = BTreeMap::new();
             for inner_pair in pair.into_inner() {
-                match inner_pair.as_rule() {
-                    Rule::pair => {
-                        for pair_inner in inner_pair.into_inner() {
-                            match pair_inner.as_rule() {
-                                Rule::chars => {
-                                    key = pair_inner.as_str();
-                                }
-                                Rule::value => {
-                                    map.insert(key.to_string(), parse_value(pair_inner)?);
-                                }
-                                _ => unreachable!(),
-                            }
-                        }
-                    }
-                    _ => unreachable!(),
-                }
+                let (key, value) = parse_pair(inner_pair)?;
+                map.insert(key, value);
             }
             JsonValue::Object(map)
         }
         Rule::value => {
-            let inner_pair = pair.into_inner().next().unwrap();
-            parse_value(inner_pair)?
+            let mut inner = pair.clone().into_inner();
+            match inner.next() {
+                Some(inner_pair) => parse_value(inner_pair)?,
+                None => return Err(span_error(&pair, "empty value")),
+            }
         }
-        v => {
-            panic!("unhandled rule: {:?}", v);
+        _ => return Err(span_error(&pair, "not a JSON value")),
+    };
+    Ok(ret)
+}
+
+fn parse_pair(pair: Pair<Rule>) -> Result<(String, JsonValue)> {
+    if pair.as_rule() != Rule::pair {
+        return Err(span_error(&pair, "not a `\"key\": value` pair"));
+    }
+    let mut inner = pair.clone().into_inner();
+    match (inner.next(), inner.next()) {
+        (Some(key), Some(value))
+            if key.as_rule() == Rule::chars && value.as_rule() == Rule::value =>
+        {
+            Ok((unescape(&key)?, parse_value(value)?))
         }
+        _ => Err(span_error(&pair, "malformed `\"key\": value` pair")),
+    }
+}
+
+// the grammar already checked the escapes, what's left is joining surrogate pairs
+fn unescape(pair: &Pair<Rule>) -> Result<String> {
+    let s = pair.as_str();
+    if !s.contains('\\') {
+        return Ok(s.to_string());
+    }
+    let mut ret = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    let hex = |chars: &mut std::str::Chars| {
+        let digits = chars.as_str().get(..4)?;
+        chars.nth(3);
+        u32::from_str_radix(digits, 16).ok()
     };
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            ret.push(c);
+            continue;
+        }
+        let c = match chars.next() {
+            Some('b') => Some('\u{8}'),
+            Some('f') => Some('\u{c}'),
+            Some('n') => Some('\n'),
+            Some('r') => Some('\r'),
+            Some('t') => Some('\t'),
+            Some('u') => match hex(&mut chars) {
+                Some(high @ 0xd800..=0xdbff) => {
+                    let low = chars.as_str().strip_prefix("\\u").and_then(|_| {
+                        chars.nth(1);
+                        hex(&mut chars)
+                    });
+                    match low {
+                        Some(low @ 0xdc00..=0xdfff) => {
+                            char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00))
+                        }
+                        _ => None,
+                    }
+                }
+                Some(unit) => char::from_u32(unit),
+                None => None,
+            },
+            c => c,
+        };
+        match c {
+            Some(c) => ret.push(c),
+            None => return Err(span_error(pair, "invalid escape")),
+        }
+    }
     Ok(ret)
 }
 
+// the offset is where the pair starts, the message names its rule and whole span
+fn span_error(pair: &Pair<Rule>, msg: &str) -> anyhow::Error {
+    let span = pair.as_span();
+    anyhow::Error::new(ParseError::UnexpectedChar {
+        offset: span.start(),
+    })
+    .context(format!(
+        "{}: {:?} at bytes {}..{}",
+        msg,
+        pair.as_rule(),
+        span.start(),
+        span.end()
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use pest::consumes_to;
@@ -118,17 +204,17 @@ mod tests {
         let s = "123";
         let parsed = JsonParser::parse(Rule::number, s)?.next().unwrap();
         let v = parse_value(parsed)?;
-        assert_eq!(v, JsonValue::Number(123.0));
+        assert_eq!(v, JsonValue::Number(Num::Int(123)));
 
         let s = "-123";
         let parsed = JsonParser::parse(Rule::number, s)?.next().unwrap();
         let v = parse_value(parsed)?;
-        assert_eq!(v, JsonValue::Number(-123.0));
+        assert_eq!(v, JsonValue::Number(Num::Int(-123)));
 
         let s = "123.45";
         let parsed = JsonParser::parse(Rule::number, s)?.next().unwrap();
         let v = parse_value(parsed)?;
-        assert_eq!(v, JsonValue::Number(123.45));
+        assert_eq!(v, JsonValue::Number(Num::Float(123.45)));
         Ok(())
     }
 
@@ -149,9 +235,9 @@ mod tests {
         assert_eq!(
             v,
             JsonValue::Array(vec![
-                JsonValue::Number(1.0),
-                JsonValue::Number(2.0),
-                JsonValue::Number(3.0)
+                JsonValue::Number(Num::Int(1)),
+                JsonValue::Number(Num::Int(2)),
+                JsonValue::Number(Num::Int(3))
             ])
         );
         Ok(())
@@ -162,16 +248,98 @@ mod tests {
         let s = r#"{"name": "John Doe", "age": 30}"#;
         let parsed = JsonParser::parse(Rule::object, s)?.next().unwrap();
         let v = parse_value(parsed)?;
-        let mut map = HashMap::new();
+        let mut map = BTreeMap::new();
         map.insert(
             "name".to_string(),
             JsonValue::String("John Doe".to_string()),
         );
-        map.insert("age".to_string(), JsonValue::Number(30.0));
+        map.insert("age".to_string(), JsonValue::Number(Num::Int(30)));
         assert_eq!(v, JsonValue::Object(map));
         Ok(())
     }
 
+    #[test]
+    fn pest_parse_should_match_winnow() -> Result<()> {
+        let s = br#"{"name": "John Doe", "marks": [90, -80, 85.1], "address": {"zip": 10001}}"#;
+        assert_eq!(parse(s)?, crate::json::parse_json(s)?);
+        assert!(parse(b"[1] 2").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn pest_parse_should_accept_what_winnow_accepts() -> Result<()> {
+        let docs = [
+            "{}",
+            "[]",
+            "42",
+            "-2.5e-3",
+            r#""s""#,
+            "true",
+            " null ",
+            r#"{"a": [], "b": {}, "c": [{}]}"#,
+            r#""a\/b\u0041\ud83d\ude00\n\"""#,
+            r#"{"\u00e9": "\\"}"#,
+        ];
+        for doc in docs {
+            assert_eq!(
+                parse(doc.as_bytes())?,
+                crate::json::parse_json(doc.as_bytes())?,
+                "{}",
+                doc
+            );
+        }
+        for doc in [r#""\ud83d""#, r#""\ude00x""#, r#"["\ud83d\u0041"]"#] {
+            assert!(parse(doc.as_bytes()).is_err(), "{}", doc);
+            assert!(crate::json::parse_json(doc.as_bytes()).is_err(), "{}", doc);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn pest_parse_error_should_show_the_line() {
+        let err = parse(b"{\"a\": [1,\n  2, }]}").unwrap_err().to_string();
+        assert!(
+            err.starts_with("invalid JSON at line 2, column 6\n"),
+            "{}",
+            err
+        );
+        assert!(err.contains("2 |   2, }]}"), "{}", err);
+        assert!(err.contains("  |      ^"), "{}", err);
+        assert!(err.contains("expected a JSON value"), "{}", err);
+    }
+
+    #[test]
+    fn pest_parse_unexpected_rule_should_fail() -> Result<()> {
+        // these used to hit the `panic!` and `unreachable!` arms
+        let parsed = JsonParser::parse(Rule::int, "42")?.next().unwrap();
+        let err = parse_value(parsed).unwrap_err();
+        assert_eq!(err.to_string(), "not a JSON value: int at bytes 0..2");
+        assert_eq!(
+            err.downcast_ref::<ParseError>(),
+            Some(&ParseError::UnexpectedChar { offset: 0 })
+        );
+
+        let parsed = JsonParser::parse(Rule::pair, r#""a": 1"#)?.next().unwrap();
+        let err = parse_value(parsed).unwrap_err();
+        assert_eq!(err.to_string(), "not a JSON value: pair at bytes 0..6");
+
+        let parsed = JsonParser::parse(Rule::value, "null")?.next().unwrap();
+        assert!(parse_pair(parsed).is_err());
+
+        // grammar-accepted but out of range for an f64
+        let err = parse(b"[1, 1e400]").unwrap_err();
+        assert_eq!(err.to_string(), "invalid number: number at bytes 4..9");
+        Ok(())
+    }
+
+    #[test]
+    fn pest_parse_empty_input_should_fail() {
+        for input in ["", "   ", "\n\t"] {
+            let err = parse(input.as_bytes()).unwrap_err();
+            assert_eq!(err.to_string(), "empty input: expected a JSON value");
+        }
+    }
+
     #[test]
     fn pest_parse_ruls_should_work() {
         parses_to! {
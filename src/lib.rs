@@ -0,0 +1,14 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+pub mod error;
+pub mod json;
+#[cfg(feature = "pest")]
+pub mod json2;
+#[cfg(feature = "nginx")]
+pub mod nginx;
+#[cfg(feature = "nginx")]
+pub mod nginx_regex;
+pub mod number;
+pub mod units;
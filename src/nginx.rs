@@ -0,0 +1,1394 @@
+#[cfg(any(feature = "flate2", feature = "rayon"))]
+use std::path::Path;
+use std::{
+    collections::HashMap,
+    fmt,
+    io::Write,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    str::FromStr,
+};
+#[cfg(feature = "flate2")]
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Read},
+};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use winnow::{
+    ascii::{float, space0},
+    combinator::{alt, delimited, eof, opt, peek, preceded, repeat, separated, terminated},
+    error::StrContext,
+    token::{take_till, take_until, take_while},
+    PResult, Parser,
+};
+
+use crate::{error::ParseError, number::parse_uint};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpMethod {
+    Get,
+    Post,
+    Put,
+    Delete,
+    Head,
+    Options,
+    Connect,
+    Trace,
+    Patch,
+}
+
+// `$upstream_cache_status`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CacheStatus {
+    Hit,
+    Miss,
+    Bypass,
+    Expired,
+    Stale,
+    Updating,
+    Revalidated,
+    Other(String),
+}
+
+// ordered by version, e.g. `proto >= HttpProto::HTTP2_0`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HttpProto {
+    HTTP1_0,
+    HTTP1_1,
+    HTTP2_0,
+    HTTP3_0,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NginxLog {
+    pub addr: IpAddr,
+    // only when `$remote_addr` is logged as `ip:port` or `[ipv6]:port`
+    pub port: Option<u16>,
+    pub date: DateTime<Utc>,
+    pub method: Option<HttpMethod>,
+    pub url: Option<String>,
+    pub protocol: Option<HttpProto>,
+    pub raw_request: String,
+    pub status: u16,
+    pub body_bytes: Option<u64>,
+    pub referer: String,
+    pub user_agent: String,
+    pub request_time: Option<f64>,
+    pub upstream_time: Option<f64>,
+    // any fields appended after the combined format, quotes stripped
+    pub extra: Vec<String>,
+    // `$host`, the second appended field after `$http_x_forwarded_for`; `-` is `None`
+    pub host: Option<String>,
+    // `$upstream_addr` and `$upstream_cache_status`, the third and fourth appended fields
+    pub upstream_addr: Option<String>,
+    pub cache_status: Option<CacheStatus>,
+}
+
+type HttpRequest = (HttpMethod, String, HttpProto);
+
+impl NginxLog {
+    // `$http_x_forwarded_for` is the first field appended to the combined format; its
+    // left-most address is the original client, `addr` is then the nearest proxy
+    pub fn client_ip(&self) -> IpAddr {
+        self.extra
+            .first()
+            .and_then(|xff| xff.split(',').next())
+            .and_then(|ip| ip.trim().parse().ok())
+            .unwrap_or(self.addr)
+    }
+
+    // `url` run through `normalize_path`, so equivalent paths group together
+    pub fn normalized_url(&self) -> Option<String> {
+        self.url.as_deref().map(normalize_path)
+    }
+
+    // the lowercased host of an absolute `scheme://[user@]host[:port]/...` referer, `None` for
+    // `-` or anything that isn't such a url
+    pub fn referer_host(&self) -> Option<String> {
+        let (scheme, rest) = self.referer.split_once("://")?;
+        let mut chars = scheme.chars();
+        if !chars.next()?.is_ascii_alphabetic()
+            || !chars.all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+        {
+            return None;
+        }
+        let authority = rest.split(['/', '?', '#']).next()?;
+        let host = authority.rsplit_once('@').map_or(authority, |(_, h)| h);
+        let host = match host.strip_prefix('[') {
+            Some(v6) => &host[..v6.find(']')? + 2],
+            None => host.split(':').next()?,
+        };
+        let valid = host.starts_with('[')
+            || host
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-._".contains(c));
+        (!host.is_empty() && valid).then(|| host.to_ascii_lowercase())
+    }
+}
+
+// a combined format line, `$remote_user` is always `-`. the times are only written when one of
+// them or an extra field is present, the extra fields are always quoted
+impl fmt::Display for NginxLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.addr, self.port) {
+            (IpAddr::V6(ip), Some(port)) => write!(f, "[{}]:{}", ip, port)?,
+            (ip, Some(port)) => write!(f, "{}:{}", ip, port)?,
+            (ip, None) => write!(f, "{}", ip)?,
+        }
+        write!(
+            f,
+            " - - [{}] \"{}\" {} ",
+            self.date.format("%d/%b/%Y:%H:%M:%S%.f %z"),
+            self.raw_request,
+            self.status
+        )?;
+        match self.body_bytes {
+            Some(n) => write!(f, "{}", n)?,
+            None => f.write_str("-")?,
+        }
+        write!(f, " \"{}\" \"{}\"", self.referer, self.user_agent)?;
+        if self.request_time.is_some() || self.upstream_time.is_some() || !self.extra.is_empty() {
+            for time in [self.request_time, self.upstream_time] {
+                match time {
+                    Some(t) => write!(f, " {}", t)?,
+                    None => f.write_str(" -")?,
+                }
+            }
+        }
+        for field in &self.extra {
+            write!(f, " \"{}\"", field)?;
+        }
+        Ok(())
+    }
+}
+
+// the field a tab-separated column maps to in `NginxLog`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Addr,
+    Date,
+    // the whole `METHOD URL PROTO` request line
+    Request,
+    Method,
+    Url,
+    Protocol,
+    Status,
+    BodyBytes,
+    Referer,
+    UserAgent,
+    RequestTime,
+    UpstreamTime,
+    Host,
+    UpstreamAddr,
+    CacheStatus,
+    // kept in `extra`
+    Extra,
+    Ignore,
+}
+
+// apache's common format is the combined format without the trailing referer and user agent,
+// a common line gets `-` for both and has no times or extra fields
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogKind {
+    Common,
+    #[default]
+    Combined,
+}
+
+pub fn parse_nginx_log(s: &str) -> Result<NginxLog> {
+    parse_access_log(s, LogKind::Combined)
+}
+
+pub fn parse_access_log(s: &str, kind: LogKind) -> Result<NginxLog> {
+    (|input: &mut &str| parse_log(input, kind))
+        .parse(s)
+        .map_err(|e| {
+            let offset = e.offset();
+            let e = if offset == s.len() {
+                ParseError::UnexpectedEof { offset }
+            } else {
+                ParseError::UnexpectedChar { offset }
+            };
+            anyhow::Error::new(e).context("invalid nginx log")
+        })
+}
+
+#[cfg(feature = "flate2")]
+pub fn parse_gzip_log(path: &Path) -> Result<impl Iterator<Item = Result<NginxLog>>> {
+    Ok(parse_gzip_reader(File::open(path)?))
+}
+
+#[cfg(feature = "flate2")]
+pub fn parse_gzip_reader(reader: impl Read) -> impl Iterator<Item = Result<NginxLog>> {
+    BufReader::new(flate2::read::GzDecoder::new(reader))
+        .lines()
+        .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(|line| parse_nginx_log(&line?))
+}
+
+// one result per non-blank line, in file order. `n_threads` of 0 lets rayon pick
+#[cfg(feature = "rayon")]
+pub fn par_parse_log_file(path: &Path, n_threads: usize) -> Result<Vec<Result<NginxLog>>> {
+    par_parse_logs(&std::fs::read_to_string(path)?, n_threads)
+}
+
+#[cfg(feature = "rayon")]
+pub fn par_parse_logs(text: &str, n_threads: usize) -> Result<Vec<Result<NginxLog>>> {
+    use rayon::prelude::*;
+
+    let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(n_threads)
+        .build()?;
+    // a line is far too cheap to be a task of its own, hand them out in chunks. collecting
+    // an indexed iterator keeps the order
+    let chunk = (lines.len() / (pool.current_num_threads() * 4)).max(64);
+    Ok(pool.install(|| {
+        lines
+            .par_chunks(chunk)
+            .flat_map_iter(|chunk| chunk.iter().map(|line| parse_nginx_log(line)))
+            .collect()
+    }))
+}
+
+// addr, date and status are required, the other fields fall back to what an absent
+// value looks like in the combined format
+pub fn parse_tsv_log(line: &str, columns: &[Column]) -> Result<NginxLog> {
+    let fields: Vec<&str> = line.trim_end_matches(['\r', '\n']).split('\t').collect();
+    if fields.len() != columns.len() {
+        return Err(anyhow!(
+            "expected {} columns, got {}",
+            columns.len(),
+            fields.len()
+        ));
+    }
+    let (mut addr, mut date, mut status) = (None, None, None);
+    let (mut method, mut url, mut protocol, mut raw_request) = (None, None, None, None);
+    // method, url and protocol columns joined back into a request line
+    let mut request_parts = vec![];
+    let mut log = NginxLog {
+        addr: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        port: None,
+        date: DateTime::default(),
+        method: None,
+        url: None,
+        protocol: None,
+        raw_request: String::new(),
+        status: 0,
+        body_bytes: None,
+        referer: "-".to_string(),
+        user_agent: "-".to_string(),
+        request_time: None,
+        upstream_time: None,
+        extra: vec![],
+        host: None,
+        upstream_addr: None,
+        cache_status: None,
+    };
+    for (column, field) in columns.iter().zip(fields) {
+        let err = |e| anyhow!("invalid {:?} column {:?}: {}", column, field, e);
+        match column {
+            Column::Addr => {
+                let (ip, port) = parse_addr.parse(field).map_err(|e| err(e.to_string()))?;
+                (addr, log.port) = (Some(ip), port);
+            }
+            Column::Date => {
+                let ts = field.trim_start_matches('[').trim_end_matches(']');
+                date = Some(parse_timestamp(ts).map_err(|e| err(e.to_string()))?);
+            }
+            Column::Request => {
+                if let Some((m, u, p)) = parse_request_line(field) {
+                    (method, url, protocol) = (Some(m), Some(u), Some(p));
+                }
+                raw_request = Some(field.to_string());
+            }
+            Column::Method => {
+                method = Some(parse_method.parse(field).map_err(|e| err(e.to_string()))?);
+                request_parts.push(field);
+            }
+            Column::Url => {
+                url = Some(field.to_string());
+                request_parts.push(field);
+            }
+            Column::Protocol => {
+                protocol = Some(
+                    parse_protocol
+                        .parse(field)
+                        .map_err(|e| err(e.to_string()))?,
+                );
+                request_parts.push(field);
+            }
+            Column::Status => {
+                status = Some(parse_status.parse(field).map_err(|e| err(e.to_string()))?)
+            }
+            Column::BodyBytes => {
+                log.body_bytes = parse_bytes.parse(field).map_err(|e| err(e.to_string()))?
+            }
+            Column::Referer => log.referer = field.to_string(),
+            Column::UserAgent => log.user_agent = field.to_string(),
+            Column::RequestTime => {
+                log.request_time = parse_time.parse(field).map_err(|e| err(e.to_string()))?
+            }
+            Column::UpstreamTime => {
+                log.upstream_time = parse_time.parse(field).map_err(|e| err(e.to_string()))?
+            }
+            Column::Host => log.host = optional_field(field),
+            Column::UpstreamAddr => log.upstream_addr = optional_field(field),
+            Column::CacheStatus => log.cache_status = parse_cache_status(field),
+            Column::Extra => log.extra.push(field.to_string()),
+            Column::Ignore => {}
+        }
+    }
+    log.addr = addr.ok_or_else(|| anyhow!("missing Addr column"))?;
+    log.date = date.ok_or_else(|| anyhow!("missing Date column"))?;
+    log.status = status.ok_or_else(|| anyhow!("missing Status column"))?;
+    log.raw_request = raw_request.unwrap_or_else(|| request_parts.join(" "));
+    (log.method, log.url, log.protocol) = (method, url, protocol);
+    Ok(log)
+}
+
+fn parse_log(input: &mut &str, kind: LogKind) -> PResult<NginxLog> {
+    let (ip, port) = parse_addr(input)?;
+    parse_ignored(input)?;
+    parse_ignored(input)?;
+    let date = parse_datetime(input)?;
+    let (request, raw_request) = parse_http(input)?;
+    let (method, url, protocol) = match request {
+        Some((method, url, protocol)) => (Some(method), Some(url), Some(protocol)),
+        None => (None, None, None),
+    };
+    let status = parse_status(input)?;
+    let body_bytes = parse_bytes(input)?;
+    let (referer, user_agent, request_time, upstream_time, extra) = match kind {
+        LogKind::Common => ("-".to_string(), "-".to_string(), None, None, vec![]),
+        LogKind::Combined => (
+            parse_quote_string(input)?,
+            parse_quote_string(input)?,
+            opt(parse_time).parse_next(input)?.flatten(),
+            opt(parse_time).parse_next(input)?.flatten(),
+            parse_extra(input)?,
+        ),
+    };
+    let field = |i: usize| extra.get(i).and_then(|f| optional_field(f));
+    let (host, upstream_addr) = (field(1), field(2));
+    let cache_status = extra.get(3).and_then(|f| parse_cache_status(f));
+    Ok(NginxLog {
+        addr: ip,
+        port,
+        date,
+        method,
+        url,
+        protocol,
+        raw_request,
+        status,
+        body_bytes,
+        referer,
+        user_agent,
+        request_time,
+        upstream_time,
+        extra,
+        host,
+        upstream_addr,
+        cache_status,
+    })
+}
+
+fn optional_field(s: &str) -> Option<String> {
+    (!s.is_empty() && s != "-").then(|| s.to_string())
+}
+
+fn parse_cache_status(s: &str) -> Option<CacheStatus> {
+    Some(match s {
+        "" | "-" => return None,
+        "HIT" => CacheStatus::Hit,
+        "MISS" => CacheStatus::Miss,
+        "BYPASS" => CacheStatus::Bypass,
+        "EXPIRED" => CacheStatus::Expired,
+        "STALE" => CacheStatus::Stale,
+        "UPDATING" => CacheStatus::Updating,
+        "REVALIDATED" => CacheStatus::Revalidated,
+        s => CacheStatus::Other(s.to_string()),
+    })
+}
+
+pub fn requests_per_ip(logs: impl Iterator<Item = NginxLog>) -> HashMap<IpAddr, u64> {
+    let mut ret = HashMap::new();
+    for log in logs {
+        *ret.entry(log.addr).or_insert(0) += 1;
+    }
+    ret
+}
+
+// rfc 1918, loopback and link-local, plus the ipv6 equivalents: unique local `fc00::/7`
+// and link-local `fe80::/10`. an ipv4-mapped ipv6 address is judged as ipv4
+pub fn is_private_ip(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => ip.is_private() || ip.is_loopback() || ip.is_link_local(),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_private_ip(&IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                ip.is_loopback() || first & 0xfe00 == 0xfc00 || first & 0xffc0 == 0xfe80
+            }
+        },
+    }
+}
+
+// drops internal traffic, judged by `client_ip` so logs behind a proxy are kept
+pub fn filter_public(logs: impl Iterator<Item = NginxLog>) -> impl Iterator<Item = NginxLog> {
+    logs.filter(|log| !is_private_ip(&log.client_ip()))
+}
+
+// logs without a host are skipped
+pub fn requests_per_host(logs: impl Iterator<Item = NginxLog>) -> HashMap<String, u64> {
+    let mut ret = HashMap::new();
+    for log in logs {
+        if let Some(host) = log.host {
+            *ret.entry(host).or_insert(0) += 1;
+        }
+    }
+    ret
+}
+
+// collapses `//`, resolves `.` and `..` (never above the root) and drops a trailing slash, `/`
+// itself stays. a query string is kept as is, anything not starting with `/` (`*`, an absolute
+// url) is returned unchanged
+pub fn normalize_path(url: &str) -> String {
+    if !url.starts_with('/') {
+        return url.to_string();
+    }
+    let (path, query) = url.split_at(url.find(['?', '#']).unwrap_or(url.len()));
+    let mut segments = vec![];
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            s => segments.push(s),
+        }
+    }
+    format!("/{}{}", segments.join("/"), query)
+}
+
+// the share of `HIT`s among the logs with a cache status, `None` if there are none
+pub fn cache_hit_ratio(logs: impl Iterator<Item = NginxLog>) -> Option<f64> {
+    let (mut hits, mut total) = (0u64, 0u64);
+    for status in logs.filter_map(|log| log.cache_status) {
+        total += 1;
+        hits += (status == CacheStatus::Hit) as u64;
+    }
+    (total > 0).then(|| hits as f64 / total as f64)
+}
+
+// the `n` most frequent referer hosts, most frequent first and ties by host. logs without a
+// referer host are skipped
+pub fn top_referers(logs: impl Iterator<Item = NginxLog>, n: usize) -> Vec<(String, u64)> {
+    let mut counts = HashMap::new();
+    for host in logs.filter_map(|log| log.referer_host()) {
+        *counts.entry(host).or_insert(0) += 1;
+    }
+    let mut ret: Vec<_> = counts.into_iter().collect();
+    ret.sort_by(|(a, x), (b, y)| y.cmp(x).then_with(|| a.cmp(b)));
+    ret.truncate(n);
+    ret
+}
+
+// logs without a parsed url are skipped, missing byte counts are counted as 0
+pub fn bytes_per_url(logs: impl Iterator<Item = NginxLog>) -> HashMap<String, u64> {
+    let mut ret = HashMap::new();
+    for log in logs {
+        if let Some(url) = log.url {
+            *ret.entry(url).or_insert(0) += log.body_bytes.unwrap_or(0);
+        }
+    }
+    ret
+}
+
+const CSV_HEADER: &str =
+    "addr,date,request,status,body_bytes,referer,user_agent,request_time,upstream_time";
+
+// rfc 4180: CRLF line endings, a field containing a comma, quote or line break is
+// quoted with the quotes doubled, absent values are empty fields
+pub fn write_csv(logs: impl Iterator<Item = NginxLog>, mut w: impl Write) -> Result<()> {
+    write!(w, "{}\r\n", CSV_HEADER)?;
+    let opt = |v: Option<String>| v.unwrap_or_default();
+    for log in logs {
+        let row = [
+            log.addr.to_string(),
+            log.date.to_rfc3339(),
+            csv_field(&log.raw_request),
+            log.status.to_string(),
+            opt(log.body_bytes.map(|b| b.to_string())),
+            csv_field(&log.referer),
+            csv_field(&log.user_agent),
+            opt(log.request_time.map(|t| t.to_string())),
+            opt(log.upstream_time.map(|t| t.to_string())),
+        ];
+        write!(w, "{}\r\n", row.join(","))?;
+    }
+    Ok(())
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+// `$remote_user` and apache's ident field, usually `-`
+fn parse_ignored(s: &mut &str) -> PResult<()> {
+    (take_till(1.., ' '), ' ').parse_next(s)?;
+    space0(s)?;
+    Ok(())
+}
+
+// ipv4 with an optional `:port`, `[ipv6]` with an optional `:port`, or a bare ipv6 address.
+// a bare ipv6 address never has a port, its last group would be indistinguishable from one
+fn parse_addr(s: &mut &str) -> PResult<(IpAddr, Option<u16>)> {
+    let port = || opt(preceded(':', parse_uint.try_map(u16::try_from)));
+    let ret = alt((
+        (
+            delimited('[', take_till(1.., ']').try_map(Ipv6Addr::from_str), ']'),
+            port(),
+        )
+            .map(|(ip, port)| (IpAddr::V6(ip), port)),
+        (parse_ipv4, port()).map(|(ip, port)| (IpAddr::V4(ip), port)),
+        take_while(2.., |c: char| c.is_ascii_hexdigit() || c == ':' || c == '.')
+            .try_map(Ipv6Addr::from_str)
+            .map(|ip| (IpAddr::V6(ip), None)),
+    ))
+    .parse_next(s)?;
+    space0(s)?;
+    Ok(ret)
+}
+
+fn parse_ipv4(s: &mut &str) -> PResult<Ipv4Addr> {
+    let ret: Vec<u8> = separated(4, parse_uint.try_map(u8::try_from), '.').parse_next(s)?;
+    Ok(Ipv4Addr::new(ret[0], ret[1], ret[2], ret[3]))
+}
+
+fn parse_datetime(s: &mut &str) -> PResult<DateTime<Utc>> {
+    let ret = delimited('[', take_until(1.., ']').try_map(parse_timestamp), ']')
+        .context(StrContext::Label("datetime"))
+        .parse_next(s)?;
+    space0(s)?;
+    Ok(ret)
+}
+
+// try the common log format ($time_local) first, then ISO8601 ($time_iso8601). the common
+// format may carry fractional seconds, `08:05:32.123`, and name UTC as `Z`, `UTC` or `GMT`
+// instead of giving an offset
+fn parse_timestamp(s: &str) -> Result<DateTime<Utc>, chrono::ParseError> {
+    const LOCAL: &str = "%d/%b/%Y:%H:%M:%S%.f";
+    let utc = ["Z", "UTC", "GMT"]
+        .iter()
+        .find_map(|tz| s.strip_suffix(tz))
+        .and_then(|local| NaiveDateTime::parse_from_str(local.trim_end(), LOCAL).ok());
+    if let Some(dt) = utc {
+        return Ok(dt.and_utc());
+    }
+    DateTime::parse_from_str(s, &format!("{} %z", LOCAL))
+        .or_else(|_| DateTime::parse_from_rfc3339(s))
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+// a malformed request line is kept raw instead of failing the whole log
+fn parse_http(s: &mut &str) -> PResult<(Option<HttpRequest>, String)> {
+    let raw: &str = delimited('"', take_until(0.., '"'), '"').parse_next(s)?;
+    space0(s)?;
+    Ok((parse_request_line(raw), raw.to_string()))
+}
+
+fn parse_request_line(raw: &str) -> Option<HttpRequest> {
+    (parse_method, parse_url, parse_protocol).parse(raw).ok()
+}
+
+fn parse_status(s: &mut &str) -> PResult<u16> {
+    let ret = parse_uint.try_map(u16::try_from).parse_next(s)?;
+    space0(s)?;
+    Ok(ret)
+}
+
+fn parse_bytes(s: &mut &str) -> PResult<Option<u64>> {
+    let ret = alt(("-".value(None), parse_uint.map(Some))).parse_next(s)?;
+    space0(s)?;
+    Ok(ret)
+}
+
+fn parse_quote_string(s: &mut &str) -> PResult<String> {
+    let ret = delimited('"', take_until(1.., '"'), '"').parse_next(s)?;
+    space0(s)?;
+    Ok(ret.to_string())
+}
+
+// a time takes up the whole field, anything else (`10.0.0.1`, `0.1,`, `nan`) is left for `extra`
+fn parse_time(s: &mut &str) -> PResult<Option<f64>> {
+    let end = || peek(alt((" ", eof)));
+    let ret = alt((
+        terminated("-", end()).value(None),
+        terminated(float.verify(|t: &f64| t.is_finite()), end()).map(Some),
+    ))
+    .parse_next(s)?;
+    space0(s)?;
+    Ok(ret)
+}
+
+fn parse_extra(s: &mut &str) -> PResult<Vec<String>> {
+    let quoted = delimited('"', take_until(0.., '"'), '"');
+    let bare = take_till(1.., ' ');
+    repeat(
+        0..,
+        terminated(alt((quoted, bare)).map(|v: &str| v.to_string()), space0),
+    )
+    .parse_next(s)
+}
+
+fn parse_method(s: &mut &str) -> PResult<HttpMethod> {
+    let ret = alt((
+        "GET", "POST", "PUT", "DELETE", "HEAD", "OPTIONS", "CONNECT", "TRACE", "PATCH",
+    ))
+    .parse_to()
+    .parse_next(s)?;
+    space0(s)?;
+    Ok(ret)
+}
+
+fn parse_url(s: &mut &str) -> PResult<String> {
+    let ret = take_until(1.., ' ').parse_next(s)?;
+    space0(s)?;
+    Ok(ret.to_string())
+}
+
+fn parse_protocol(s: &mut &str) -> PResult<HttpProto> {
+    let ret = alt(("HTTP/1.0", "HTTP/1.1", "HTTP/2.0", "HTTP/3.0"))
+        .parse_to()
+        .parse_next(s)?;
+    space0(s)?;
+    Ok(ret)
+}
+
+impl FromStr for HttpProto {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "HTTP/1.0" => Ok(HttpProto::HTTP1_0),
+            "HTTP/1.1" => Ok(HttpProto::HTTP1_1),
+            "HTTP/2.0" => Ok(HttpProto::HTTP2_0),
+            "HTTP/3.0" => Ok(HttpProto::HTTP3_0),
+            _ => Err(anyhow::anyhow!("invalid protocol")),
+        }
+    }
+}
+
+impl HttpProto {
+    pub fn major(&self) -> u8 {
+        match self {
+            HttpProto::HTTP1_0 | HttpProto::HTTP1_1 => 1,
+            HttpProto::HTTP2_0 => 2,
+            HttpProto::HTTP3_0 => 3,
+        }
+    }
+
+    pub fn minor(&self) -> u8 {
+        match self {
+            HttpProto::HTTP1_1 => 1,
+            _ => 0,
+        }
+    }
+}
+
+impl FromStr for HttpMethod {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "GET" => Ok(HttpMethod::Get),
+            "POST" => Ok(HttpMethod::Post),
+            "PUT" => Ok(HttpMethod::Put),
+            "DELETE" => Ok(HttpMethod::Delete),
+            "HEAD" => Ok(HttpMethod::Head),
+            "OPTIONS" => Ok(HttpMethod::Options),
+            "CONNECT" => Ok(HttpMethod::Connect),
+            "TRACE" => Ok(HttpMethod::Trace),
+            "PATCH" => Ok(HttpMethod::Patch),
+            _ => Err(anyhow::anyhow!("invalid method")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn parse_addr_should_work() -> anyhow::Result<()> {
+        let mut s = "93.180.71.3";
+        let ip = parse_addr(&mut s).unwrap();
+        assert_eq!(s, "");
+        assert_eq!(ip, (IpAddr::V4(Ipv4Addr::new(93, 180, 71, 3)), None));
+
+        let v6 = IpAddr::V6("2001:db8::1".parse()?);
+        let cases = [
+            (
+                "93.180.71.3:8080",
+                (IpAddr::V4(Ipv4Addr::new(93, 180, 71, 3)), Some(8080)),
+            ),
+            ("[2001:db8::1]:443", (v6, Some(443))),
+            ("[2001:db8::1]", (v6, None)),
+            ("2001:db8::1", (v6, None)),
+            ("::1", (IpAddr::V6("::1".parse()?), None)),
+            (
+                "::ffff:10.0.0.1",
+                (IpAddr::V6("::ffff:10.0.0.1".parse()?), None),
+            ),
+        ];
+        for (input, expected) in cases {
+            let mut s = input;
+            assert_eq!(parse_addr(&mut s).ok(), Some(expected), "{}", input);
+            assert_eq!(s, "", "{}", input);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn parse_addr_invalid_should_fail() {
+        for s in ["256.180.71.3", "[2001:db8::1", "[93.180.71.3]", "::g", "-"] {
+            assert!(parse_addr.parse(s).is_err(), "{}", s);
+        }
+        assert!(parse_addr.parse("93.180.71.3:65536").is_err());
+        assert!(parse_addr.parse("[::1]:").is_err());
+    }
+
+    #[test]
+    fn parse_datetime_should_work() -> anyhow::Result<()> {
+        let mut s = "[17/May/2015:08:05:32 +0000]";
+        let dt = parse_datetime(&mut s).unwrap();
+        assert_eq!(s, "");
+        assert_eq!(dt, Utc.with_ymd_and_hms(2015, 5, 17, 8, 5, 32).unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn parse_datetime_iso8601_should_work() -> anyhow::Result<()> {
+        let mut s = "[2015-05-17T08:05:32+00:00]";
+        let dt = parse_datetime(&mut s).unwrap();
+        assert_eq!(s, "");
+        assert_eq!(dt, Utc.with_ymd_and_hms(2015, 5, 17, 8, 5, 32).unwrap());
+
+        let mut s = "[2015-05-17T10:05:32+02:00]";
+        let dt = parse_datetime(&mut s).unwrap();
+        assert_eq!(dt, Utc.with_ymd_and_hms(2015, 5, 17, 8, 5, 32).unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn parse_datetime_fraction_and_utc_should_work() -> anyhow::Result<()> {
+        let expected = Utc.with_ymd_and_hms(2015, 5, 17, 8, 5, 32).unwrap();
+        let millis = expected + chrono::Duration::milliseconds(123);
+        let cases = [
+            ("[17/May/2015:08:05:32.123 +0000]", millis),
+            ("[17/May/2015:10:05:32.123 +0200]", millis),
+            (
+                "[17/May/2015:08:05:32.123456 +0000]",
+                expected + chrono::Duration::microseconds(123_456),
+            ),
+            ("[17/May/2015:08:05:32 Z]", expected),
+            ("[17/May/2015:08:05:32Z]", expected),
+            ("[17/May/2015:08:05:32.123 UTC]", millis),
+            ("[17/May/2015:08:05:32 GMT]", expected),
+            ("[2015-05-17T08:05:32.123Z]", millis),
+            ("[2015-05-17T08:05:32Z]", expected),
+        ];
+        for (s, dt) in cases {
+            assert_eq!(parse_datetime(&mut &*s).unwrap(), dt, "{}", s);
+        }
+        for s in [
+            "[17/May/2015:08:05:32 CEST]",
+            "[17/May/2015:08:05:32.]",
+            "[17/May/2015:08:05 Z]",
+        ] {
+            assert!(parse_datetime(&mut &*s).is_err(), "{}", s);
+        }
+
+        // the fraction survives a round trip through `Display`
+        let s =
+            r#"93.180.71.3 - - [17/May/2015:08:05:32.123 +0000] "GET / HTTP/1.1" 200 0 "-" "-""#;
+        let log = parse_nginx_log(s)?;
+        assert_eq!(log.date, millis);
+        assert_eq!(log.to_string(), s);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_datetime_invalid_should_fail() {
+        let mut s = "[yesterday]";
+        let err = parse_datetime(&mut s).unwrap_err();
+        assert!(err.to_string().contains("datetime"));
+    }
+
+    #[test]
+    fn parse_nginx_log_with_timing_should_work() -> anyhow::Result<()> {
+        let s = r#"93.180.71.3 - - [17/May/2015:08:05:32 +0000] "GET /downloads/product_1 HTTP/1.1" 304 0 "-" "Debian APT-HTTP/1.3 (0.8.16~exp12ubuntu10.21)" 0.123 0.120"#;
+        let log = parse_nginx_log(s).unwrap();
+        assert_eq!(log.request_time, Some(0.123));
+        assert_eq!(log.upstream_time, Some(0.120));
+        Ok(())
+    }
+
+    #[test]
+    fn parse_nginx_log_without_timing_should_work() -> anyhow::Result<()> {
+        let s = r#"93.180.71.3 - - [17/May/2015:08:05:32 +0000] "GET /downloads/product_1 HTTP/1.1" 304 0 "-" "Debian APT-HTTP/1.3 (0.8.16~exp12ubuntu10.21)""#;
+        let log = parse_nginx_log(s).unwrap();
+        assert_eq!(log.request_time, None);
+        assert_eq!(log.upstream_time, None);
+        Ok(())
+    }
+
+    #[test]
+    fn http_proto_version_should_work() {
+        use HttpProto::*;
+        assert!(HTTP1_0 < HTTP1_1);
+        assert!(HTTP1_1 < HTTP2_0);
+        assert!(HTTP2_0 < HTTP3_0);
+        assert!(HTTP3_0 >= HTTP2_0);
+        let versions = [HTTP1_0, HTTP1_1, HTTP2_0, HTTP3_0].map(|p| (p.major(), p.minor()));
+        assert_eq!(versions, [(1, 0), (1, 1), (2, 0), (3, 0)]);
+        // versions sort the same way as the variants
+        let mut protos = [HTTP3_0, HTTP1_1, HTTP2_0, HTTP1_0];
+        protos.sort();
+        assert!(protos
+            .windows(2)
+            .all(|w| (w[0].major(), w[0].minor()) < (w[1].major(), w[1].minor())));
+    }
+
+    #[test]
+    fn parse_http_should_work() -> anyhow::Result<()> {
+        let mut s = r#""GET /downloads/product_1 HTTP/1.1" "#;
+        let (request, raw) = parse_http(&mut s).unwrap();
+        assert_eq!(s, "");
+        let (method, url, protocol) = request.unwrap();
+        assert!(matches!(method, HttpMethod::Get));
+        assert_eq!(url, "/downloads/product_1");
+        assert!(matches!(protocol, HttpProto::HTTP1_1));
+        assert_eq!(raw, "GET /downloads/product_1 HTTP/1.1");
+        Ok(())
+    }
+
+    #[test]
+    fn parse_http_with_garbage_should_keep_raw() -> anyhow::Result<()> {
+        let s = r#"93.180.71.3 - - [17/May/2015:08:05:32 +0000] "\x16\x03\x01" 400 166 "-" "-""#;
+        let log = parse_nginx_log(s).unwrap();
+        assert!(log.method.is_none());
+        assert!(log.url.is_none());
+        assert!(log.protocol.is_none());
+        assert_eq!(log.raw_request, r"\x16\x03\x01");
+        assert_eq!(log.status, 400);
+        Ok(())
+    }
+
+    #[test]
+    fn requests_per_ip_should_work() -> anyhow::Result<()> {
+        let lines = [
+            r#"93.180.71.3 - - [17/May/2015:08:05:32 +0000] "GET /downloads/product_1 HTTP/1.1" 304 0 "-" "-""#,
+            r#"80.91.33.133 - - [17/May/2015:08:05:24 +0000] "GET /downloads/product_1 HTTP/1.1" 304 0 "-" "-""#,
+            r#"93.180.71.3 - - [17/May/2015:08:05:23 +0000] "GET /downloads/product_1 HTTP/1.1" 304 0 "-" "-""#,
+            r#"93.180.71.3 - - [17/May/2015:08:05:24 +0000] "GET /downloads/product_2 HTTP/1.1" 200 490 "-" "-""#,
+        ];
+        let logs = lines.iter().map(|s| parse_nginx_log(s).unwrap());
+        let counts = requests_per_ip(logs);
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts[&IpAddr::V4(Ipv4Addr::new(93, 180, 71, 3))], 3);
+        assert_eq!(counts[&IpAddr::V4(Ipv4Addr::new(80, 91, 33, 133))], 1);
+        Ok(())
+    }
+
+    #[test]
+    fn bytes_per_url_should_work() -> anyhow::Result<()> {
+        let lines = [
+            r#"93.180.71.3 - - [17/May/2015:08:05:32 +0000] "GET /downloads/product_1 HTTP/1.1" 200 1024 "-" "-""#,
+            r#"80.91.33.133 - - [17/May/2015:08:05:24 +0000] "GET /downloads/product_1 HTTP/1.1" 304 - "-" "-""#,
+            r#"93.180.71.3 - - [17/May/2015:08:05:23 +0000] "GET /downloads/product_2 HTTP/1.1" 200 490 "-" "-""#,
+            r#"93.180.71.3 - - [17/May/2015:08:05:24 +0000] "GET /downloads/product_1 HTTP/1.1" 200 26 "-" "-""#,
+        ];
+        let logs = lines.iter().map(|s| parse_nginx_log(s).unwrap());
+        let bytes = bytes_per_url(logs);
+        assert_eq!(bytes.len(), 2);
+        assert_eq!(bytes["/downloads/product_1"], 1050);
+        assert_eq!(bytes["/downloads/product_2"], 490);
+        Ok(())
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn parse_gzip_reader_should_work() -> anyhow::Result<()> {
+        use std::io::Write;
+
+        let lines = concat!(
+            r#"93.180.71.3 - - [17/May/2015:08:05:32 +0000] "GET /downloads/product_1 HTTP/1.1" 304 0 "-" "-""#,
+            "\n",
+            r#"80.91.33.133 - - [17/May/2015:08:05:24 +0000] "GET /downloads/product_2 HTTP/1.1" 200 490 "-" "-""#,
+            "\n",
+        );
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(lines.as_bytes())?;
+        let data = encoder.finish()?;
+
+        let logs = parse_gzip_reader(data.as_slice()).collect::<Result<Vec<_>>>()?;
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[0].url.as_deref(), Some("/downloads/product_1"));
+        assert_eq!(logs[1].addr, IpAddr::V4(Ipv4Addr::new(80, 91, 33, 133)));
+        Ok(())
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_parse_logs_should_work() -> anyhow::Result<()> {
+        let text = (0..1000)
+            .map(|i| match i % 7 {
+                0 => "\n".to_string(),
+                3 => format!("garbage {}\n", i),
+                _ => format!(
+                    r#"10.0.{}.{} - - [17/May/2015:08:05:32 +0000] "GET /downloads/{} HTTP/1.1" 200 {} "-" "-""#,
+                    i / 256,
+                    i % 256,
+                    i,
+                    i * 10
+                ) + "\n",
+            })
+            .collect::<String>();
+        let sequential: Vec<_> = text
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| parse_nginx_log(l).map_err(|e| e.to_string()))
+            .collect();
+        for n_threads in [1, 4, 0] {
+            let parallel: Vec<_> = par_parse_logs(&text, n_threads)?
+                .into_iter()
+                .map(|r| r.map_err(|e| e.to_string()))
+                .collect();
+            assert_eq!(parallel, sequential);
+        }
+        assert_eq!(sequential.len(), 857);
+        assert_eq!(sequential.iter().filter(|r| r.is_err()).count(), 143);
+
+        let path = std::env::temp_dir().join(format!("gammar-par-{}.log", std::process::id()));
+        std::fs::write(&path, &text)?;
+        let from_file = par_parse_log_file(&path, 2);
+        std::fs::remove_file(&path)?;
+        assert_eq!(from_file?.len(), sequential.len());
+        Ok(())
+    }
+
+    #[test]
+    fn parse_nginx_log_with_extra_fields_should_work() -> anyhow::Result<()> {
+        let s = r#"93.180.71.3 - - [17/May/2015:08:05:32 +0000] "GET /downloads/product_1 HTTP/1.1" 304 0 "-" "Debian APT-HTTP/1.3 (0.8.16~exp12ubuntu10.21)" "203.0.113.9, 10.0.0.1" "example.com""#;
+        let log = parse_nginx_log(s)?;
+        assert_eq!(
+            log.user_agent,
+            "Debian APT-HTTP/1.3 (0.8.16~exp12ubuntu10.21)"
+        );
+        assert_eq!(log.extra, vec!["203.0.113.9, 10.0.0.1", "example.com"]);
+
+        let s = r#"93.180.71.3 - - [17/May/2015:08:05:32 +0000] "GET /downloads/product_1 HTTP/1.1" 304 0 "-" "-" 0.005 - "" example.com"#;
+        let log = parse_nginx_log(s)?;
+        assert_eq!(log.request_time, Some(0.005));
+        assert_eq!(log.upstream_time, None);
+        assert_eq!(log.extra, vec!["", "example.com"]);
+
+        // unquoted extra fields that merely start like a time
+        let prefix =
+            r#"93.180.71.3 - - [17/May/2015:08:05:32 +0000] "GET / HTTP/1.1" 200 0 "-" "-""#;
+        let log = parse_nginx_log(&format!("{} 10.0.0.1", prefix))?;
+        assert_eq!(log.request_time, None);
+        assert_eq!(log.upstream_time, None);
+        assert_eq!(log.extra, vec!["10.0.0.1"]);
+        let log = parse_nginx_log(&format!("{} 0.1, 0.2", prefix))?;
+        assert_eq!(log.request_time, None);
+        assert_eq!(log.extra, vec!["0.1,", "0.2"]);
+        let log = parse_nginx_log(&format!("{} 0.1 inf nan", prefix))?;
+        assert_eq!(log.request_time, Some(0.1));
+        assert_eq!(log.upstream_time, None);
+        assert_eq!(log.extra, vec!["inf", "nan"]);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_tsv_log_should_work() -> anyhow::Result<()> {
+        use Column::*;
+        let columns = [Addr, Date, Method, Url, Protocol, Status, BodyBytes, Extra];
+        let s = "93.180.71.3\t17/May/2015:08:05:32 +0000\tGET\t/downloads/product_1\tHTTP/1.1\t304\t-\texample.com";
+        let log = parse_tsv_log(s, &columns)?;
+        assert_eq!(log.addr, IpAddr::V4(Ipv4Addr::new(93, 180, 71, 3)));
+        assert_eq!(
+            log.date,
+            Utc.with_ymd_and_hms(2015, 5, 17, 8, 5, 32).unwrap()
+        );
+        assert_eq!(log.method, Some(HttpMethod::Get));
+        assert_eq!(log.url.as_deref(), Some("/downloads/product_1"));
+        assert_eq!(log.raw_request, "GET /downloads/product_1 HTTP/1.1");
+        assert_eq!(log.status, 304);
+        assert_eq!(log.body_bytes, None);
+        assert_eq!(log.referer, "-");
+        assert_eq!(log.extra, vec!["example.com"]);
+
+        let columns = [Ignore, Status, Addr, Date, Request];
+        let s = "x\t200\t10.0.0.1\t[2024-01-02T03:04:05Z]\tGET / HTTP/2.0";
+        let log = parse_tsv_log(s, &columns)?;
+        assert_eq!(log.protocol, Some(HttpProto::HTTP2_0));
+        assert_eq!(log.url.as_deref(), Some("/"));
+
+        assert!(parse_tsv_log("10.0.0.1\t200", &[Addr, Status]).is_err());
+        assert!(parse_tsv_log("10.0.0.1", &[Addr, Status]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parse_nginx_log_addr_port_should_work() -> anyhow::Result<()> {
+        let rest = r#"- - [17/May/2015:08:05:32 +0000] "GET / HTTP/1.1" 200 0 "-" "-""#;
+        let log = parse_nginx_log(&format!("[2001:db8::1]:443 {}", rest))?;
+        assert_eq!(log.addr, IpAddr::V6("2001:db8::1".parse()?));
+        assert_eq!(log.port, Some(443));
+        let log = parse_nginx_log(&format!("93.180.71.3:8080 {}", rest))?;
+        assert_eq!(log.addr, IpAddr::V4(Ipv4Addr::new(93, 180, 71, 3)));
+        assert_eq!(log.port, Some(8080));
+        let log = parse_nginx_log(&format!("93.180.71.3 {}", rest))?;
+        assert_eq!(log.port, None);
+
+        let columns = [Column::Addr, Column::Date, Column::Status];
+        let log = parse_tsv_log("[::1]:80\t[2024-01-02T03:04:05Z]\t200", &columns)?;
+        assert_eq!((log.addr, log.port), (IpAddr::V6("::1".parse()?), Some(80)));
+        Ok(())
+    }
+
+    #[test]
+    fn parse_access_log_should_work() -> anyhow::Result<()> {
+        let common = r#"127.0.0.1 - frank [10/Oct/2000:13:55:36 -0700] "GET /apache_pb.gif HTTP/1.0" 200 2326"#;
+        let log = parse_access_log(common, LogKind::Common)?;
+        assert_eq!(log.addr, IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)));
+        assert_eq!(
+            log.date,
+            Utc.with_ymd_and_hms(2000, 10, 10, 20, 55, 36).unwrap()
+        );
+        assert_eq!(log.url.as_deref(), Some("/apache_pb.gif"));
+        assert_eq!(log.protocol, Some(HttpProto::HTTP1_0));
+        assert_eq!(log.status, 200);
+        assert_eq!(log.body_bytes, Some(2326));
+        assert_eq!((log.referer.as_str(), log.user_agent.as_str()), ("-", "-"));
+        assert!(log.extra.is_empty());
+        // a common line is just a combined line cut short
+        assert!(parse_access_log(common, LogKind::Combined).is_err());
+
+        let combined = format!(
+            r#"{} "http://example.com/start.html" "Mozilla/4.08""#,
+            common
+        );
+        let log = parse_access_log(&combined, LogKind::Combined)?;
+        assert_eq!(log.referer, "http://example.com/start.html");
+        assert_eq!(log.user_agent, "Mozilla/4.08");
+        assert_eq!(log, parse_nginx_log(&combined)?);
+        assert!(parse_access_log(&combined, LogKind::Common).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn display_should_round_trip() -> anyhow::Result<()> {
+        let lines = [
+            r#"93.180.71.3 - - [17/May/2015:08:05:32 +0000] "GET /downloads/product_1 HTTP/1.1" 304 0 "-" "Debian APT-HTTP/1.3 (0.8.16~exp12ubuntu10.21)""#,
+            r#"[2001:db8::1]:443 - - [17/May/2015:08:05:24 +0000] "GET /downloads/product_2 HTTP/1.1" 200 - "http://example.com/" "curl/8.0" 0.005 - "203.0.113.9, 10.0.0.2" "example.com""#,
+            r#"10.0.0.1:8080 - - [17/May/2015:08:05:24 +0000] "\x16\x03\x01" 400 157 "-" "-" - - "-""#,
+        ];
+        for line in lines {
+            let log = parse_nginx_log(line)?;
+            let shown = log.to_string();
+            let reparsed = parse_nginx_log(&shown)?;
+            assert_eq!(reparsed.addr, log.addr);
+            assert_eq!(reparsed.port, log.port);
+            assert_eq!(reparsed.date, log.date);
+            assert_eq!(reparsed.method, log.method);
+            assert_eq!(reparsed.url, log.url);
+            assert_eq!(reparsed.protocol, log.protocol);
+            assert_eq!(reparsed.raw_request, log.raw_request);
+            assert_eq!(reparsed.status, log.status);
+            assert_eq!(reparsed.body_bytes, log.body_bytes);
+            assert_eq!(reparsed.referer, log.referer);
+            assert_eq!(reparsed.user_agent, log.user_agent);
+            assert_eq!(reparsed.request_time, log.request_time);
+            assert_eq!(reparsed.upstream_time, log.upstream_time);
+            assert_eq!(reparsed.extra, log.extra);
+            assert_eq!(reparsed.host, log.host);
+            assert_eq!(reparsed.upstream_addr, log.upstream_addr);
+            assert_eq!(reparsed.cache_status, log.cache_status);
+        }
+        assert_eq!(parse_nginx_log(lines[0])?.to_string(), lines[0]);
+
+        // an ISO8601 date is written back in the common log format
+        let s = r#"10.0.0.1 - - [2015-05-17T10:05:32+02:00] "GET / HTTP/1.1" 200 0 "-" "-""#;
+        assert_eq!(
+            parse_nginx_log(s)?.to_string(),
+            r#"10.0.0.1 - - [17/May/2015:08:05:32 +0000] "GET / HTTP/1.1" 200 0 "-" "-""#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn client_ip_should_work() -> anyhow::Result<()> {
+        let s = r#"10.0.0.1 - - [17/May/2015:08:05:32 +0000] "GET / HTTP/1.1" 200 0 "-" "-" "203.0.113.9, 10.0.0.2""#;
+        let log = parse_nginx_log(s)?;
+        assert_eq!(log.client_ip(), IpAddr::V4(Ipv4Addr::new(203, 0, 113, 9)));
+
+        let s = r#"10.0.0.1 - - [17/May/2015:08:05:32 +0000] "GET / HTTP/1.1" 200 0 "-" "-" "-""#;
+        let log = parse_nginx_log(s)?;
+        assert_eq!(log.client_ip(), log.addr);
+
+        let s = r#"10.0.0.1 - - [17/May/2015:08:05:32 +0000] "GET / HTTP/1.1" 200 0 "-" "-""#;
+        let log = parse_nginx_log(s)?;
+        assert_eq!(log.client_ip(), IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)));
+        Ok(())
+    }
+
+    #[test]
+    fn is_private_ip_should_work() {
+        for ip in [
+            "10.0.0.1",
+            "172.16.5.4",
+            "192.168.1.1",
+            "127.0.0.1",
+            "169.254.0.1",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:10.0.0.1",
+        ] {
+            assert!(is_private_ip(&ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in [
+            "93.180.71.3",
+            "172.32.0.1",
+            "2001:db8::1",
+            "::ffff:93.180.71.3",
+        ] {
+            assert!(!is_private_ip(&ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn filter_public_should_work() -> anyhow::Result<()> {
+        let lines = [
+            r#"93.180.71.3 - - [17/May/2015:08:05:32 +0000] "GET / HTTP/1.1" 200 0 "-" "-""#,
+            r#"127.0.0.1 - - [17/May/2015:08:05:32 +0000] "GET /health HTTP/1.1" 200 0 "-" "-""#,
+            r#"10.0.0.1 - - [17/May/2015:08:05:32 +0000] "GET / HTTP/1.1" 200 0 "-" "-" "203.0.113.9""#,
+        ];
+        let logs = lines
+            .iter()
+            .map(|l| parse_nginx_log(l))
+            .collect::<Result<Vec<_>>>()?;
+        let addrs: Vec<_> = filter_public(logs.into_iter()).map(|l| l.addr).collect();
+        assert_eq!(
+            addrs,
+            [
+                IpAddr::V4(Ipv4Addr::new(93, 180, 71, 3)),
+                IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parse_host_should_work() -> anyhow::Result<()> {
+        let prefix =
+            r#"93.180.71.3 - - [17/May/2015:08:05:32 +0000] "GET / HTTP/1.1" 200 0 "-" "-""#;
+        let log = parse_nginx_log(&format!(r#"{} "-" "example.com""#, prefix))?;
+        assert_eq!(log.host.as_deref(), Some("example.com"));
+        let log = parse_nginx_log(&format!(r#"{} "-" "-""#, prefix))?;
+        assert_eq!(log.host, None);
+        let log = parse_nginx_log(prefix)?;
+        assert_eq!(log.host, None);
+
+        let columns = [Column::Addr, Column::Date, Column::Status, Column::Host];
+        let log = parse_tsv_log(
+            "10.0.0.1\t[2024-01-02T03:04:05Z]\t200\tapi.example.com",
+            &columns,
+        )?;
+        assert_eq!(log.host.as_deref(), Some("api.example.com"));
+        Ok(())
+    }
+
+    #[test]
+    fn parse_upstream_fields_should_work() -> anyhow::Result<()> {
+        let prefix = r#"93.180.71.3 - - [17/May/2015:08:05:32 +0000] "GET / HTTP/1.1" 200 0 "-" "-" 0.010 0.008"#;
+        let log = parse_nginx_log(&format!(
+            r#"{} "-" "example.com" "10.0.0.5:8080" "HIT""#,
+            prefix
+        ))?;
+        assert_eq!(log.upstream_addr.as_deref(), Some("10.0.0.5:8080"));
+        assert_eq!(log.cache_status, Some(CacheStatus::Hit));
+        let log = parse_nginx_log(&format!(r#"{} "-" "example.com" "-" "BYPASS""#, prefix))?;
+        assert_eq!(log.upstream_addr, None);
+        assert_eq!(log.cache_status, Some(CacheStatus::Bypass));
+        let log = parse_nginx_log(&format!(r#"{} "-" "example.com" "-" "WARM""#, prefix))?;
+        assert_eq!(
+            log.cache_status,
+            Some(CacheStatus::Other("WARM".to_string()))
+        );
+
+        let log = parse_nginx_log(&format!(r#"{} "-" "example.com""#, prefix))?;
+        assert_eq!(log.upstream_addr, None);
+        assert_eq!(log.cache_status, None);
+
+        let columns = [
+            Column::Addr,
+            Column::Date,
+            Column::Status,
+            Column::CacheStatus,
+        ];
+        let log = parse_tsv_log("10.0.0.1\t[2024-01-02T03:04:05Z]\t200\tMISS", &columns)?;
+        assert_eq!(log.cache_status, Some(CacheStatus::Miss));
+        Ok(())
+    }
+
+    #[test]
+    fn normalize_path_should_work() -> anyhow::Result<()> {
+        let cases = [
+            ("/a//b/", "/a/b"),
+            ("/a/../b", "/b"),
+            ("/", "/"),
+            ("//", "/"),
+            ("/a/./b/.", "/a/b"),
+            ("/../../a", "/a"),
+            ("/a/b/..", "/a"),
+            ("/a//b/?x=1//2", "/a/b?x=1//2"),
+            ("/a/#frag", "/a#frag"),
+            ("*", "*"),
+            ("http://example.com//a", "http://example.com//a"),
+        ];
+        for (path, expected) in cases {
+            assert_eq!(normalize_path(path), expected, "{}", path);
+        }
+
+        let s = r#"93.180.71.3 - - [17/May/2015:08:05:32 +0000] "GET /downloads//product_1/ HTTP/1.1" 200 0 "-" "-""#;
+        let log = parse_nginx_log(s)?;
+        assert_eq!(
+            log.normalized_url().as_deref(),
+            Some("/downloads/product_1")
+        );
+        let s = r#"93.180.71.3 - - [17/May/2015:08:05:32 +0000] "-" 400 0 "-" "-""#;
+        assert_eq!(parse_nginx_log(s)?.normalized_url(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn cache_hit_ratio_should_work() -> anyhow::Result<()> {
+        let prefix =
+            r#"93.180.71.3 - - [17/May/2015:08:05:32 +0000] "GET / HTTP/1.1" 200 0 "-" "-" - -"#;
+        let lines = [
+            format!(r#"{} "-" "-" "-" "HIT""#, prefix),
+            format!(r#"{} "-" "-" "-" "MISS""#, prefix),
+            format!(r#"{} "-" "-" "-" "HIT""#, prefix),
+            format!(r#"{} "-" "-" "-" "EXPIRED""#, prefix),
+            prefix.to_string(),
+        ];
+        let logs = lines
+            .iter()
+            .map(|l| parse_nginx_log(l))
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(cache_hit_ratio(logs.iter().cloned()), Some(0.5));
+        assert_eq!(cache_hit_ratio(logs.into_iter().skip(4)), None);
+        Ok(())
+    }
+
+    #[test]
+    fn requests_per_host_should_work() -> anyhow::Result<()> {
+        let prefix =
+            r#"93.180.71.3 - - [17/May/2015:08:05:32 +0000] "GET / HTTP/1.1" 200 0 "-" "-""#;
+        let lines = [
+            format!(r#"{} "-" "a.example.com""#, prefix),
+            format!(r#"{} "-" "b.example.com""#, prefix),
+            format!(r#"{} "-" "a.example.com""#, prefix),
+            prefix.to_string(),
+        ];
+        let logs = lines
+            .iter()
+            .map(|l| parse_nginx_log(l))
+            .collect::<Result<Vec<_>>>()?;
+        let ret = requests_per_host(logs.into_iter());
+        assert_eq!(ret.len(), 2);
+        assert_eq!(ret["a.example.com"], 2);
+        assert_eq!(ret["b.example.com"], 1);
+        Ok(())
+    }
+
+    #[test]
+    fn referer_host_should_work() -> anyhow::Result<()> {
+        let prefix = r#"93.180.71.3 - - [17/May/2015:08:05:32 +0000] "GET / HTTP/1.1" 200 0"#;
+        let cases = [
+            ("https://example.com/page", Some("example.com")),
+            ("http://Example.COM", Some("example.com")),
+            ("http://user@example.com:8080/?a=1", Some("example.com")),
+            ("https://[2001:db8::1]:443/x", Some("[2001:db8::1]")),
+            (
+                "android-app://com.google.android.gm/",
+                Some("com.google.android.gm"),
+            ),
+            ("-", None),
+            ("example.com/page", None),
+            ("http:///page", None),
+            ("://example.com", None),
+            ("http://exa mple.com/", None),
+        ];
+        for (referer, expected) in cases {
+            let log = parse_nginx_log(&format!(r#"{} "{}" "-""#, prefix, referer))?;
+            assert_eq!(log.referer_host().as_deref(), expected, "{}", referer);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn top_referers_should_work() -> anyhow::Result<()> {
+        let prefix = r#"93.180.71.3 - - [17/May/2015:08:05:32 +0000] "GET / HTTP/1.1" 200 0"#;
+        let referers = [
+            "https://b.com/1",
+            "https://a.com/",
+            "-",
+            "http://b.com/2",
+            "https://c.com/",
+            "https://a.com/x",
+            "https://b.com",
+        ];
+        let logs = referers
+            .iter()
+            .map(|r| parse_nginx_log(&format!(r#"{} "{}" "-""#, prefix, r)))
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(
+            top_referers(logs.into_iter(), 2),
+            [("b.com".to_string(), 3), ("a.com".to_string(), 2)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn write_csv_should_work() -> anyhow::Result<()> {
+        let lines = [
+            r#"93.180.71.3 - - [17/May/2015:08:05:32 +0000] "GET /downloads/product_1 HTTP/1.1" 304 0 "-" "Debian APT-HTTP/1.3 (0.8.16~exp12ubuntu10.21)""#,
+            r#"80.91.33.133 - - [17/May/2015:08:05:24 +0000] "GET /downloads/product_2 HTTP/1.1" 200 - "http://example.com/?a=1,b=2" "Mozilla/5.0 (compatible, bot)" 0.005 -"#,
+        ];
+        let logs = lines
+            .iter()
+            .map(|l| parse_nginx_log(l))
+            .collect::<Result<Vec<_>>>()?;
+        let mut out = Vec::new();
+        write_csv(logs.into_iter(), &mut out)?;
+        assert_eq!(
+            String::from_utf8(out)?,
+            "addr,date,request,status,body_bytes,referer,user_agent,request_time,upstream_time\r\n\
+             93.180.71.3,2015-05-17T08:05:32+00:00,GET /downloads/product_1 HTTP/1.1,304,0,-,Debian APT-HTTP/1.3 (0.8.16~exp12ubuntu10.21),,\r\n\
+             80.91.33.133,2015-05-17T08:05:24+00:00,GET /downloads/product_2 HTTP/1.1,200,,\"http://example.com/?a=1,b=2\",\"Mozilla/5.0 (compatible, bot)\",0.005,\r\n"
+        );
+        assert_eq!(csv_field(r#"say "hi""#), r#""say ""hi""""#);
+        Ok(())
+    }
+}
@@ -0,0 +1,40 @@
+use std::sync::LazyLock;
+
+use anyhow::{anyhow, Result};
+use regex::Regex;
+
+// compiled once on first use instead of on every line
+static LOG_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"^(?<ip>\S+)\s+\S+\s+\S+\s+\[(?<date>[^\]]+)\]\s+"(?<method>\S+)\s+(?<url>\S+)\s+(?<proto>[^"]+)"\s+(?<status>\d+)\s+(?<bytes>\d+)\s+"(?<referer>[^"]+)"\s+"(?<ua>[^"]+)"$"#,
+    )
+    .unwrap()
+});
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NginxLog {
+    pub addr: String,
+    pub date: String,
+    pub method: String,
+    pub url: String,
+    pub protocol: String,
+    pub status: u16,
+    pub body_bytes: u64,
+    pub referer: String,
+    pub user_agent: String,
+}
+
+pub fn parse_nginx_log(s: &str) -> Result<NginxLog> {
+    let cap = LOG_RE.captures(s).ok_or(anyhow!("invalid log format"))?;
+    Ok(NginxLog {
+        addr: cap["ip"].to_string(),
+        date: cap["date"].to_string(),
+        method: cap["method"].to_string(),
+        url: cap["url"].to_string(),
+        protocol: cap["proto"].to_string(),
+        status: cap["status"].parse()?,
+        body_bytes: cap["bytes"].parse()?,
+        referer: cap["referer"].to_string(),
+        user_agent: cap["ua"].to_string(),
+    })
+}
@@ -0,0 +1,57 @@
+use winnow::{
+    error::{ErrMode, ErrorKind, ParserError},
+    stream::{AsBStr, AsChar, Stream, StreamIsPartial},
+    token::take_while,
+    PResult, Parser,
+};
+
+// scan the whole digit run at once and convert it in a single pass,
+// overflowing u64 is a hard error rather than a backtrack
+pub fn parse_uint<Input, Error>(input: &mut Input) -> PResult<u64, Error>
+where
+    Input: StreamIsPartial + Stream,
+    <Input as Stream>::Token: AsChar,
+    <Input as Stream>::Slice: AsBStr,
+    Error: ParserError<Input>,
+{
+    let digits = take_while(1.., AsChar::is_dec_digit).parse_next(input)?;
+    digits
+        .as_bstr()
+        .iter()
+        .try_fold(0u64, |acc, d| {
+            acc.checked_mul(10)?.checked_add((d - b'0') as u64)
+        })
+        .ok_or_else(|| ErrMode::Cut(Error::from_error_kind(input, ErrorKind::Verify)))
+}
+
+#[cfg(test)]
+mod tests {
+    use winnow::error::ContextError;
+
+    use super::*;
+
+    #[test]
+    fn parse_uint_should_work() -> PResult<(), ContextError> {
+        let mut input = "12345,";
+        assert_eq!(parse_uint(&mut input)?, 12345);
+        assert_eq!(input, ",");
+
+        let mut input = "18446744073709551615";
+        assert_eq!(parse_uint(&mut input)?, u64::MAX);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_uint_overflow_should_fail() {
+        let mut input = "18446744073709551616";
+        let ret: PResult<u64, ContextError> = parse_uint(&mut input);
+        assert!(matches!(ret, Err(ErrMode::Cut(_))));
+    }
+
+    #[test]
+    fn parse_uint_without_digits_should_fail() {
+        let mut input = "abc";
+        let ret: PResult<u64, ContextError> = parse_uint(&mut input);
+        assert!(matches!(ret, Err(ErrMode::Backtrack(_))));
+    }
+}
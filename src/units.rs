@@ -0,0 +1,186 @@
+use core::time::Duration;
+
+use winnow::{
+    combinator::{opt, preceded},
+    error::{ErrMode, ErrorKind, ParserError},
+    stream::{AsBStr, AsChar, Compare, Stream, StreamIsPartial},
+    token::take_while,
+    PResult, Parser,
+};
+
+// SI prefixes are powers of 1000, binary ones powers of 1024
+const BYTE_UNITS: [(&[u8], u128); 9] = [
+    (b"B", 1),
+    (b"KB", 1_000),
+    (b"MB", 1_000_000),
+    (b"GB", 1_000_000_000),
+    (b"TB", 1_000_000_000_000),
+    (b"KiB", 1 << 10),
+    (b"MiB", 1 << 20),
+    (b"GiB", 1 << 30),
+    (b"TiB", 1 << 40),
+];
+
+// in nanoseconds
+const DURATION_UNITS: [(&[u8], u128); 8] = [
+    (b"ns", 1),
+    (b"us", 1_000),
+    ("µs".as_bytes(), 1_000),
+    (b"ms", 1_000_000),
+    (b"s", 1_000_000_000),
+    (b"m", 60 * 1_000_000_000),
+    (b"h", 60 * 60 * 1_000_000_000),
+    (b"d", 24 * 60 * 60 * 1_000_000_000),
+];
+
+// `10KB`, `2.5MiB`, a bare number is a byte count. a fraction of a byte is dropped
+pub fn parse_byte_size<Input, Error>(input: &mut Input) -> PResult<u64, Error>
+where
+    Input: StreamIsPartial + Stream + Compare<char>,
+    <Input as Stream>::Token: AsChar,
+    <Input as Stream>::Slice: AsBStr,
+    Error: ParserError<Input>,
+{
+    let quantity = parse_quantity(input, &BYTE_UNITS, Some(1))?;
+    u64::try_from(quantity).map_err(|_| cut(input))
+}
+
+// `300ms`, `1.5s`, the unit is required. anything below a nanosecond is dropped
+pub fn parse_duration<Input, Error>(input: &mut Input) -> PResult<Duration, Error>
+where
+    Input: StreamIsPartial + Stream + Compare<char>,
+    <Input as Stream>::Token: AsChar,
+    <Input as Stream>::Slice: AsBStr,
+    Error: ParserError<Input>,
+{
+    let nanos = parse_quantity(input, &DURATION_UNITS, None)?;
+    let secs = u64::try_from(nanos / 1_000_000_000).map_err(|_| cut(input))?;
+    Ok(Duration::new(secs, (nanos % 1_000_000_000) as u32))
+}
+
+// a decimal number directly followed by one of `units`, scaled to the base unit. a number too
+// big to represent or an unknown unit is a hard error rather than a backtrack
+fn parse_quantity<Input, Error>(
+    input: &mut Input,
+    units: &[(&[u8], u128)],
+    default: Option<u128>,
+) -> PResult<u128, Error>
+where
+    Input: StreamIsPartial + Stream + Compare<char>,
+    <Input as Stream>::Token: AsChar,
+    <Input as Stream>::Slice: AsBStr,
+    Error: ParserError<Input>,
+{
+    // the digits as one integer, divided back by 10^(fraction length) once scaled
+    let int = take_while(1.., AsChar::is_dec_digit).parse_next(input)?;
+    let mantissa = push_digits(Some(0), int.as_bstr());
+    let frac = opt(preceded('.', take_while(1.., AsChar::is_dec_digit))).parse_next(input)?;
+    let (mantissa, scale) = match frac {
+        Some(frac) => (
+            push_digits(mantissa, frac.as_bstr()),
+            10u128.checked_pow(frac.as_bstr().len() as u32),
+        ),
+        None => (mantissa, Some(1)),
+    };
+
+    let start = input.checkpoint();
+    let suffix = take_while(0.., |c: <Input as Stream>::Token| {
+        c.as_char().is_alphabetic()
+    })
+    .parse_next(input)?;
+    let factor = match suffix.as_bstr() {
+        [] => default,
+        suffix => units.iter().find(|(u, _)| *u == suffix).map(|(_, f)| *f),
+    };
+    let Some(factor) = factor else {
+        input.reset(&start);
+        return Err(cut(input));
+    };
+
+    match (mantissa, scale) {
+        (Some(mantissa), Some(scale)) => mantissa
+            .checked_mul(factor)
+            .map(|v| v / scale)
+            .ok_or_else(|| cut(input)),
+        _ => Err(cut(input)),
+    }
+}
+
+fn push_digits(acc: Option<u128>, digits: &[u8]) -> Option<u128> {
+    digits.iter().try_fold(acc?, |acc, d| {
+        acc.checked_mul(10)?.checked_add((d - b'0') as u128)
+    })
+}
+
+fn cut<Input, Error>(input: &mut Input) -> ErrMode<Error>
+where
+    Input: Stream,
+    Error: ParserError<Input>,
+{
+    ErrMode::Cut(Error::from_error_kind(input, ErrorKind::Verify))
+}
+
+#[cfg(test)]
+mod tests {
+    use winnow::error::ContextError;
+
+    use super::*;
+
+    #[test]
+    fn parse_byte_size_should_work() -> PResult<(), ContextError> {
+        let cases = [
+            ("512", 512),
+            ("1B", 1),
+            ("10KB", 10_000),
+            ("10KiB", 10_240),
+            ("2.5MiB", 2_621_440),
+            ("1.5GB", 1_500_000_000),
+            ("2TiB", 2 << 40),
+            ("0.0005KB", 0),
+        ];
+        for (s, expected) in cases {
+            assert_eq!(parse_byte_size(&mut &*s)?, expected, "{}", s);
+        }
+
+        let mut input = "10KB, 20KB";
+        assert_eq!(parse_byte_size(&mut input)?, 10_000);
+        assert_eq!(input, ", 20KB");
+        Ok(())
+    }
+
+    #[test]
+    fn parse_duration_should_work() -> PResult<(), ContextError> {
+        let cases = [
+            ("300ms", Duration::from_millis(300)),
+            ("1.5s", Duration::from_millis(1500)),
+            ("250us", Duration::from_micros(250)),
+            ("250µs", Duration::from_micros(250)),
+            ("7ns", Duration::from_nanos(7)),
+            ("2m", Duration::from_secs(120)),
+            ("1.25h", Duration::from_secs(4500)),
+            ("1d", Duration::from_secs(86_400)),
+        ];
+        for (s, expected) in cases {
+            assert_eq!(parse_duration(&mut &*s)?, expected, "{}", s);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn parse_units_invalid_should_fail() {
+        let mut input = "10XB";
+        let ret: PResult<u64, ContextError> = parse_byte_size(&mut input);
+        assert!(matches!(ret, Err(ErrMode::Cut(_))));
+        assert_eq!(input, "XB");
+
+        let ret: PResult<u64, ContextError> = parse_byte_size(&mut "10kb");
+        assert!(ret.is_err());
+        // a duration needs a unit
+        let ret: PResult<Duration, ContextError> = parse_duration(&mut "300");
+        assert!(matches!(ret, Err(ErrMode::Cut(_))));
+        let ret: PResult<Duration, ContextError> = parse_duration(&mut "ms");
+        assert!(matches!(ret, Err(ErrMode::Backtrack(_))));
+        let ret: PResult<u64, ContextError> = parse_byte_size(&mut "100000000TB");
+        assert!(ret.is_err());
+    }
+}
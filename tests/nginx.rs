@@ -0,0 +1,53 @@
+#![cfg(feature = "nginx")]
+
+use std::net::{IpAddr, Ipv4Addr};
+
+use chrono::{TimeZone, Utc};
+use gammar::{
+    error::ParseError,
+    nginx::{parse_nginx_log, HttpMethod, HttpProto},
+};
+
+#[test]
+fn parse_nginx_log_should_work() -> anyhow::Result<()> {
+    let s = r#"93.180.71.3 - - [17/May/2015:08:05:32 +0000] "GET /downloads/product_1 HTTP/1.1" 304 0 "-" "Debian APT-HTTP/1.3 (0.8.16~exp12ubuntu10.21)""#;
+    let log = parse_nginx_log(s)?;
+    assert_eq!(log.addr, IpAddr::V4(Ipv4Addr::new(93, 180, 71, 3)));
+    assert_eq!(
+        log.date,
+        Utc.with_ymd_and_hms(2015, 5, 17, 8, 5, 32).unwrap()
+    );
+    assert_eq!(log.method, Some(HttpMethod::Get));
+    assert_eq!(log.url.as_deref(), Some("/downloads/product_1"));
+    assert_eq!(log.protocol, Some(HttpProto::HTTP1_1));
+    assert_eq!(log.status, 304);
+    assert_eq!(log.body_bytes, Some(0));
+    assert_eq!(log.referer, "-");
+    assert_eq!(
+        log.user_agent,
+        "Debian APT-HTTP/1.3 (0.8.16~exp12ubuntu10.21)"
+    );
+    Ok(())
+}
+
+#[test]
+fn parse_invalid_nginx_log_should_fail() {
+    let err = parse_nginx_log("not a log line").unwrap_err();
+    assert!(err.to_string().starts_with("invalid nginx log"));
+}
+
+#[test]
+fn parse_nginx_log_error_should_have_offset() {
+    let err =
+        parse_nginx_log("93.180.71.3 - - [17/May/2015:08:05:32 +0000] \"GET / HTTP/1.1\" abc")
+            .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<ParseError>(),
+        Some(&ParseError::UnexpectedChar { offset: 62 })
+    );
+    let err = parse_nginx_log("93.180.71.3 - - ").unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<ParseError>(),
+        Some(&ParseError::UnexpectedEof { offset: 16 })
+    );
+}
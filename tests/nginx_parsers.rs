@@ -0,0 +1,72 @@
+#![cfg(feature = "nginx")]
+
+use std::net::IpAddr;
+
+use gammar::{nginx, nginx_regex};
+
+const METHODS: [&str; 9] = [
+    "GET", "POST", "PUT", "DELETE", "HEAD", "OPTIONS", "CONNECT", "TRACE", "PATCH",
+];
+const PROTOCOLS: [&str; 4] = ["HTTP/1.0", "HTTP/1.1", "HTTP/2.0", "HTTP/3.0"];
+const STATUSES: [u16; 6] = [200, 204, 301, 304, 404, 500];
+const URLS: [&str; 3] = ["/", "/downloads/product_1", "/search?q=rust&page=2"];
+const USER_AGENTS: [&str; 2] = [
+    "Debian APT-HTTP/1.3 (0.8.16~exp12ubuntu10.21)",
+    "Mozilla/5.0 (X11; Linux x86_64)",
+];
+
+// a tiny deterministic generator so failures are reproducible
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        self.0 >> 33
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.next() as usize % items.len()]
+    }
+}
+
+fn generate_line(rng: &mut Lcg) -> String {
+    let ip = format!(
+        "{}.{}.{}.{}",
+        rng.next() % 256,
+        rng.next() % 256,
+        rng.next() % 256,
+        rng.next() % 256
+    );
+    format!(
+        r#"{} - - [17/May/2015:08:05:{:02} +0000] "{} {} {}" {} {} "-" "{}""#,
+        ip,
+        rng.next() % 60,
+        rng.pick(&METHODS),
+        rng.pick(&URLS),
+        rng.pick(&PROTOCOLS),
+        rng.pick(&STATUSES),
+        rng.next() % 100_000,
+        rng.pick(&USER_AGENTS),
+    )
+}
+
+#[test]
+fn regex_and_winnow_parsers_should_agree() -> anyhow::Result<()> {
+    let mut rng = Lcg(42);
+    for _ in 0..500 {
+        let line = generate_line(&mut rng);
+        let expected = nginx_regex::parse_nginx_log(&line)?;
+        let log = nginx::parse_nginx_log(&line)?;
+
+        assert_eq!(log.addr, expected.addr.parse::<IpAddr>()?, "{}", line);
+        assert_eq!(log.method, Some(expected.method.parse()?), "{}", line);
+        assert_eq!(log.url, Some(expected.url), "{}", line);
+        assert_eq!(log.protocol, Some(expected.protocol.parse()?), "{}", line);
+        assert_eq!(log.status, expected.status, "{}", line);
+        assert_eq!(log.body_bytes, Some(expected.body_bytes), "{}", line);
+    }
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use gammar::{
+    error::ParseError,
+    json::{parse_json, parse_json_with_opts, ParseOptions},
+};
+
+// every construct here is accepted by some lenient JSON dialect but not by RFC 8259
+fn assert_rejected(cases: &[&str]) {
+    for input in cases {
+        let err = parse_json(input.as_bytes()).expect_err(input);
+        assert!(
+            err.downcast_ref::<ParseError>().is_some(),
+            "{}: {}",
+            input,
+            err
+        );
+        let err = parse_json_with_opts(input.as_bytes(), &ParseOptions::default());
+        assert!(err.is_err(), "{}", input);
+    }
+}
+
+#[test]
+fn strict_should_reject_comments() {
+    assert_rejected(&[
+        "// comment\n1",
+        "/* comment */ 1",
+        "1 // comment",
+        "[1, /* two */ 2]",
+        "{\"a\" /* key */: 1}",
+        "{\"a\": 1 // rest\n}",
+    ]);
+}
+
+#[test]
+fn strict_should_reject_trailing_commas() {
+    assert_rejected(&["[1, 2,]", "[1,]", "[,]", "{\"a\": 1,}", "{,}", "[[1,],]"]);
+}
+
+#[test]
+fn strict_should_reject_single_quotes() {
+    assert_rejected(&["'a'", "['a']", "{'a': 1}", "{\"a\": 'b'}"]);
+}
+
+#[test]
+fn strict_should_reject_lenient_numbers() {
+    assert_rejected(&[
+        "01",
+        "-01",
+        "00.5",
+        "[1, 007]",
+        ".5",
+        "-.5",
+        "[.5]",
+        "5.",
+        "+1",
+        "0x10",
+        "NaN",
+        "Infinity",
+        "-Infinity",
+    ]);
+}
+
+#[test]
+fn strict_should_reject_other_extensions() {
+    assert_rejected(&[
+        "{a: 1}",
+        "TRUE",
+        "False",
+        "Null",
+        "\"a\tb\"",
+        "\"\\x\"",
+        "\"\\ud800\"",
+        "[1 2]",
+        "{\"a\" 1}",
+    ]);
+}
+
+#[test]
+fn relaxed_should_accept_its_extensions() -> anyhow::Result<()> {
+    let opts = ParseOptions {
+        relaxed: true,
+        ..Default::default()
+    };
+    for input in [
+        "/* comment */ 1",
+        "['a', TRUE] // done",
+        "{'a': Null}",
+        "[01, -007.5]",
+    ] {
+        parse_json_with_opts(input.as_bytes(), &opts)?;
+    }
+    Ok(())
+}